@@ -1,38 +1,32 @@
 advent_of_code::solution!(1);
 
+use advent_of_code::matcher::Matcher;
+
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+const WORDS: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
 pub fn part_one(input: &str) -> Option<u32> {
-    Some(input.lines().map(first_last_digit_concat).sum())
+    let matcher = Matcher::new(DIGITS.into_iter().zip(0..));
+    Some(input.lines().map(|l| calibration_value(&matcher, l)).sum())
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
-    Some(
-        input
-            .replace("zero", "zero0zero")
-            .replace("one", "one1one")
-            .replace("two", "two2two")
-            .replace("three", "three3three")
-            .replace("four", "four4four")
-            .replace("five", "five5five")
-            .replace("six", "six6six")
-            .replace("seven", "seven7seven")
-            .replace("eight", "eight8eight")
-            .replace("nine", "nine9nine")
-            .lines()
-            .map(first_last_digit_concat)
-            .sum(),
-    )
+    let matcher = Matcher::new(
+        DIGITS
+            .into_iter()
+            .zip(0..)
+            .chain(WORDS.into_iter().zip(0..)),
+    );
+    Some(input.lines().map(|l| calibration_value(&matcher, l)).sum())
 }
 
-fn first_last_digit_concat(in_string: &str) -> u32 {
-    let digits: Vec<u32> = in_string
-        .chars()
-        .filter_map(|char| char.to_digit(10))
-        .collect();
-    if let Some(first) = digits.first() {
-        first * 10 + digits.last().unwrap()
-    } else {
-        0
-    }
+/// Joins the first and last digits found in `line`, or returns 0 if it has none.
+fn calibration_value(matcher: &Matcher<u32>, line: &str) -> u32 {
+    matcher
+        .first_last(line)
+        .map_or(0, |(first, last)| first.value * 10 + last.value)
 }
 
 #[cfg(test)]
@@ -42,9 +36,9 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(
-            &advent_of_code::template::read_file("examples", DAY)
+            advent_of_code::template::read_file("examples", DAY)
                 .split("\n\n")
-                .nth(0)
+                .next()
                 .unwrap(),
         );
         assert_eq!(result, Some(142));
@@ -53,11 +47,19 @@ mod tests {
     #[test]
     fn test_part_two() {
         let result: Option<u32> = part_two(
-            &advent_of_code::template::read_file("examples", DAY)
+            advent_of_code::template::read_file("examples", DAY)
                 .split("\n\n")
                 .nth(1)
                 .unwrap(),
         );
         assert_eq!(result, Some(281));
     }
+
+    #[test]
+    fn test_overlapping_words() {
+        assert_eq!(part_two("eightwo\n"), Some(82));
+        assert_eq!(part_two("oneight\nsevenine"), Some(18 + 79));
+        assert_eq!(part_two("twone\nabc\n"), Some(21));
+        assert_eq!(part_one("twone\n"), Some(0));
+    }
 }
@@ -1,7 +1,11 @@
+use advent_of_code::parse::{self, ParseError};
+
 advent_of_code::solution!(2);
 
+type Game<'a> = Vec<Vec<(usize, &'a str)>>;
+
 pub fn part_one(input: &str) -> Option<u32> {
-    let games = get_games(input);
+    let games = parse::report(get_games(input))?;
     Some(
         games
             .iter()
@@ -24,7 +28,7 @@ pub fn part_one(input: &str) -> Option<u32> {
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
-    let games = get_games(input);
+    let games = parse::report(get_games(input))?;
     Some(
         games
             .iter()
@@ -47,25 +51,17 @@ pub fn part_two(input: &str) -> Option<u32> {
     )
 }
 
-fn get_games(input: &str) -> Vec<Vec<Vec<(usize, &str)>>> {
-    input
-        .lines()
-        .map(|line| {
-            let (_, info) = line.split_once(": ").unwrap();
-            let reveals = info.split("; ");
-            reveals
-                .map(|reveal| {
-                    reveal
-                        .split(", ")
-                        .map(|item| {
-                            let (amt, colour) = item.split_once(' ').unwrap();
-                            (amt.parse::<usize>().unwrap(), colour)
-                        })
-                        .collect()
-                })
-                .collect()
+fn get_games(input: &str) -> Result<Vec<Game<'_>>, ParseError> {
+    parse::each_line(input, |line| {
+        let (_, info) = parse::key_value(line, ": ")?;
+        parse::each_item(info, "; ", |reveal| {
+            parse::each_item(reveal, ", ", |item| {
+                let (amt, colour) = parse::key_value(item, " ")?;
+                Ok((parse::value(amt)?, colour))
+            })
         })
-        .collect()
+        .map_err(|err| err.within(line, info))
+    })
 }
 
 #[cfg(test)]
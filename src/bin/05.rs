@@ -1,88 +1,170 @@
-use std::ops::Range;
-
-use itertools::Itertools;
+use advent_of_code::parse::{self, ParseError, ParseErrorKind};
+use advent_of_code::ranges::RangeSet;
 
 advent_of_code::solution!(5);
 
 pub fn part_one(input: &str) -> Option<i64> {
-    let (seeds, maps) = parse_input(input);
-    let out = seeds.into_iter().map(|seed| {
-        maps.iter().fold(seed, |seed, map| {
-            map.iter()
-                .find_map(|(source, dest)| {
-                    let offset = source.start - dest.start;
-                    dest.contains(&seed).then_some(seed + offset)
-                })
-                .unwrap_or(seed)
-        })
-    });
-    Some(out.min().unwrap())
+    let almanac = parse::report(Almanac::new(input))?;
+    let location = almanac.seed_to_location();
+    almanac.seeds.iter().map(|&seed| location.get(seed)).min()
 }
 
 pub fn part_two(input: &str) -> Option<i64> {
-    let (seed_pairs, maps) = parse_input(input);
-    let mut seeds_ranges = seeds_to_ranges(seed_pairs);
-
-    for map in maps.iter() {
-        let mut new_ranges: Vec<Range<i64>> = vec![];
-
-        for range in seeds_ranges.iter() {
-            let mut curr = range.clone();
-            for (source, dest) in map.iter() {
-                let offset = source.start - dest.start;
-                if curr.start <= curr.end && curr.start <= dest.end && dest.start <= curr.end {
-                    if curr.start < dest.start {
-                        new_ranges.push(curr.start..dest.start);
-                        curr.start = dest.start;
-                    }
-                    if curr.end <= dest.end {
-                        new_ranges.push(curr.start + offset..curr.end + offset);
-                        curr.start = curr.end + 1;
-                    } else {
-                        new_ranges.push(curr.start + offset..dest.end + offset);
-                        curr.start = dest.end;
-                    }
-                }
+    let almanac = parse::report(Almanac::new(input))?;
+    let seeds = almanac.seeds.chunks(2).map(|s| s[0]..s[0] + s[1]).collect();
+    almanac.seed_to_location().min_over(&seeds)
+}
+
+/// The seeds to plant, in pairs, and the maps from seeds to soil, soil to fertilizer and so on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Almanac {
+    pub seeds: Vec<i64>,
+    pub maps: Vec<PiecewiseMap>,
+}
+
+impl Almanac {
+    pub fn new(input: &str) -> Result<Self, ParseError> {
+        let (seed_raw, maps_raw) = parse::key_value(input, "\n\n")?;
+        let seeds = parse::ints(seed_raw)?;
+        if seeds.len() % 2 == 1 {
+            return Err(ParseError::new(
+                1,
+                seed_raw.trim_end().chars().count() + 1,
+                ParseErrorKind::WrongLength {
+                    expected: seeds.len() + 1,
+                    found: seeds.len(),
+                },
+            ));
+        }
+        let maps = parse::each_block(maps_raw, |map| {
+            let (_, lines) = parse::key_value(map, "\n")?;
+            let ranges = parse::each_line(lines, |l| {
+                let [dest, source, len] = parse::exactly(l, parse::list_of::<i64>(l, " ")?)?;
+                Ok((dest, source, len))
+            })
+            .map_err(|err| err.within(map, lines))?;
+            Ok(PiecewiseMap::from_ranges(ranges))
+        })
+        .map_err(|err| err.within(input, maps_raw))?;
+        Ok(Self { seeds, maps })
+    }
+
+    /// Composes every map into one taking each seed straight to its location.
+    pub fn seed_to_location(&self) -> PiecewiseMap {
+        self.maps
+            .iter()
+            .fold(PiecewiseMap::identity(), |map, next| map.then(next))
+    }
+}
+
+/// A map adding a constant offset on each piece, stored as normalised `(start, offset)` pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiecewiseMap {
+    pieces: Vec<(i64, i64)>,
+}
+
+impl PiecewiseMap {
+    pub fn identity() -> Self {
+        Self {
+            pieces: vec![(i64::MIN, 0)],
+        }
+    }
+
+    /// Builds the map sending each non-overlapping `source` range to `dest`, others to themselves.
+    pub fn from_ranges(ranges: impl IntoIterator<Item = (i64, i64, i64)>) -> Self {
+        let mut ranges = ranges.into_iter().collect::<Vec<_>>();
+        ranges.sort_unstable_by_key(|&(_, source, _)| source);
+        let mut pieces = vec![(i64::MIN, 0)];
+        for (dest, source, len) in ranges {
+            pieces.push((source, dest - source));
+            pieces.push((source + len, 0));
+        }
+        Self::from_pieces(pieces)
+    }
+
+    /// Normalises `pieces`, sorted by first value, keeping the last of any with the same start.
+    fn from_pieces(pieces: Vec<(i64, i64)>) -> Self {
+        let mut normalised: Vec<(i64, i64)> = Vec::with_capacity(pieces.len());
+        for (start, offset) in pieces {
+            if normalised.last().is_some_and(|&(last, _)| last == start) {
+                normalised.pop();
             }
-            if curr.start <= curr.end {
-                new_ranges.push(curr);
+            if normalised.last().is_none_or(|&(_, last)| last != offset) {
+                normalised.push((start, offset));
             }
         }
-        seeds_ranges = new_ranges;
+        Self { pieces: normalised }
     }
 
-    seeds_ranges.iter().map(|r| r.start).min()
-}
+    /// Returns each piece as its half-open range of values and its offset.
+    pub fn pieces(&self) -> impl Iterator<Item = (i64, i64, i64)> + '_ {
+        let ends = self.pieces.iter().skip(1).map(|&(start, _)| start);
+        self.pieces
+            .iter()
+            .zip(ends.chain([i64::MAX]))
+            .map(|(&(start, offset), end)| (start, end, offset))
+    }
+
+    /// Returns the index of the piece holding `value`.
+    fn piece_index(&self, value: i64) -> usize {
+        self.pieces.partition_point(|&(start, _)| start <= value) - 1
+    }
+
+    pub fn get(&self, value: i64) -> i64 {
+        value + self.pieces[self.piece_index(value)].1
+    }
+
+    /// Returns the map applying `self` and then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        let mut pieces = vec![];
+        for (start, end, offset) in self.pieces() {
+            let (low, high) = (start + offset, end + offset);
+            let overlapping = next
+                .pieces()
+                .skip(next.piece_index(low))
+                .take_while(|&(next_start, _, _)| next_start < high);
+            for (next_start, _, next_offset) in overlapping {
+                pieces.push((next_start.max(low) - offset, offset + next_offset));
+            }
+        }
+        Self::from_pieces(pieces)
+    }
 
-fn parse_input(input: &str) -> (Vec<i64>, Vec<Vec<(Range<i64>, Range<i64>)>>) {
-    let (seed_raw, maps_raw) = input.split_once("\n\n").unwrap();
-    let seeds = seed_raw
-        .split_whitespace()
-        .skip(1)
-        .map(|x| x.parse().unwrap())
-        .collect();
-    let maps = maps_raw
-        .split("\n\n")
-        .map(|map| {
-            map.lines()
-                .skip(1)
-                .map(|l| {
-                    let nums = l
-                        .split_whitespace()
-                        .map(|x| x.parse::<i64>().unwrap())
-                        .collect::<Vec<_>>();
-                    (nums[0]..nums[0] + nums[2], nums[1]..nums[1] + nums[2])
-                })
-                .sorted_by(|(_, a_dest), (_, b_dest)| a_dest.start.cmp(&b_dest.start))
-                .collect()
+    /// Returns the map undoing `self`, or [`None`] if two values map to the same one.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut images = self
+            .pieces()
+            .map(|(start, end, offset)| (start + offset, end + offset, offset))
+            .collect::<Vec<_>>();
+        images.sort_unstable();
+        images.windows(2).all(|w| w[0].1 == w[1].0).then(|| {
+            Self::from_pieces(
+                images
+                    .iter()
+                    .map(|&(start, _, offset)| (start, -offset))
+                    .collect(),
+            )
         })
-        .collect();
+    }
 
-    (seeds, maps)
-}
+    /// Returns the values `values` map to.
+    pub fn image(&self, values: &RangeSet<i64>) -> RangeSet<i64> {
+        self.pieces()
+            .map(|(start, end, offset)| values.intersection(&(start..end).into()).offset(offset))
+            .fold(RangeSet::new(), |image, piece| image.union(&piece))
+    }
 
-fn seeds_to_ranges(seed_pairs: Vec<i64>) -> Vec<Range<i64>> {
-    seed_pairs.chunks(2).map(|s| s[0]..s[0] + s[1]).collect()
+    /// Returns the smallest value any of `values` maps to, checking only the first in each piece.
+    pub fn min_over(&self, values: &RangeSet<i64>) -> Option<i64> {
+        let boundaries = self.pieces.iter().map(|&(start, _)| start);
+        values
+            .ranges()
+            .iter()
+            .map(|range| range.start)
+            .chain(boundaries.filter(|&start| values.contains(start)))
+            .map(|value| self.get(value))
+            .min()
+    }
 }
 
 #[cfg(test)]
@@ -100,4 +182,51 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(46));
     }
+
+    #[test]
+    fn test_piecewise_map() {
+        let almanac = Almanac::new(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        let soil = &almanac.maps[0];
+        assert_eq!(soil.pieces, [(i64::MIN, 0), (50, 2), (98, -48), (100, 0)]);
+        assert_eq!(
+            [79, 14, 55, 13].map(|seed| soil.get(seed)),
+            [81, 14, 57, 13]
+        );
+
+        let location = almanac.seed_to_location();
+        for seed in -5..120 {
+            let stepwise = almanac.maps.iter().fold(seed, |value, map| map.get(value));
+            assert_eq!(location.get(seed), stepwise);
+        }
+        assert_eq!(location.get(82), 46);
+
+        let seed = location.inverse().unwrap();
+        assert_eq!(seed.get(46), 82);
+        assert_eq!(seed.then(&location), PiecewiseMap::identity());
+
+        let seeds = [79..93, 55..68].into_iter().collect();
+        let locations = location.image(&seeds);
+        assert_eq!(locations.len(), 27);
+        assert_eq!(locations.min(), Some(46));
+        assert_eq!(location.min_over(&seeds), Some(46));
+        assert_eq!(seed.image(&locations), seeds);
+    }
+
+    #[test]
+    fn test_odd_seeds() {
+        let input = "seeds: 79 14 55\n\nseed-to-soil map:\n50 98 2\n";
+        assert_eq!(
+            Almanac::new(input).unwrap_err().to_string(),
+            "line 1, column 16: expected 4 values, found 3"
+        );
+    }
+
+    #[test]
+    fn test_inverse_of_merging_map() {
+        let merging = PiecewiseMap::from_ranges([(10, 0, 5)]);
+        assert_eq!(merging.inverse(), None);
+        let swap = PiecewiseMap::from_ranges([(5, 0, 5), (0, 5, 5)]);
+        assert_eq!(swap.inverse(), Some(swap.clone()));
+        assert_eq!(swap.then(&swap), PiecewiseMap::identity());
+    }
 }
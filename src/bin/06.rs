@@ -1,5 +1,8 @@
 use std::str::SplitAsciiWhitespace;
 
+use num::integer::Roots;
+use num::Integer;
+
 advent_of_code::solution!(6);
 
 pub fn part_one(input: &str) -> Option<i64> {
@@ -28,7 +31,7 @@ pub fn part_two(input: &str) -> Option<i64> {
     Some(num_options)
 }
 
-fn parse_lines(input: &str) -> Vec<SplitAsciiWhitespace> {
+fn parse_lines(input: &str) -> Vec<SplitAsciiWhitespace<'_>> {
     input
         .lines()
         .filter(|l| !l.is_empty())
@@ -39,23 +42,44 @@ fn parse_lines(input: &str) -> Vec<SplitAsciiWhitespace> {
 fn race_win_options(times: Vec<i64>, distances: Vec<i64>) -> i64 {
     times
         .into_iter()
-        .zip(distances.into_iter())
-        .map(|(t_max, max_dist)| {
-            (1..t_max).fold(0, |acc, t_charge| {
-                let dist = t_charge * (t_max - t_charge);
-                if dist > max_dist {
-                    acc + 1
-                } else {
-                    acc
-                }
-            })
-        })
+        .zip(distances)
+        .map(|(t_max, max_dist)| winning_charges(t_max, max_dist))
         .product()
 }
 
+/// Counts the whole numbers of milliseconds `c` to hold the button for in a race lasting
+/// `time` that go further than `record`, that is `c * (time - c) > record`.
+///
+/// The winning charges lie strictly between the roots of `c² - time·c + record`, which
+/// are `(time ± √(time² - 4·record)) / 2`. The floor of the lower root is found with an
+/// exact integer square root and nudged up to the first winning charge, and the winners
+/// are symmetric about `time / 2`. Nothing is ever negative, so this works for unsigned
+/// and arbitrary precision integers as well as `i64`.
+fn winning_charges<T: Integer + Roots + Clone>(time: T, record: T) -> T {
+    let two = T::one() + T::one();
+    let four = two.clone() * two.clone();
+    let (squared, limit) = (time.clone() * time.clone(), four * record.clone());
+    if squared < limit {
+        return T::zero();
+    }
+    let root = (squared - limit).sqrt();
+    let mut charge = (time.clone() - root) / two.clone();
+    while two.clone() * charge.clone() <= time
+        && charge.clone() * (time.clone() - charge.clone()) <= record
+    {
+        charge = charge + T::one();
+    }
+    if two.clone() * charge.clone() > time {
+        T::zero()
+    } else {
+        time + T::one() - two * charge
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::BigUint;
 
     #[test]
     fn test_part_one() {
@@ -68,4 +92,29 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(71503));
     }
+
+    #[test]
+    fn test_winning_charges_match_brute_force() {
+        for time in 0..80i64 {
+            for record in 0..time * time / 4 + 3 {
+                let brute = (0..=time)
+                    .filter(|&charge| charge * (time - charge) > record)
+                    .count() as i64;
+                assert_eq!(winning_charges(time, record), brute, "{time} {record}");
+                assert_eq!(winning_charges(time as u128, record as u128), brute as u128);
+                assert_eq!(
+                    winning_charges(BigUint::from(time as u64), BigUint::from(record as u64)),
+                    BigUint::from(brute as u64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_winning_charges_squared_past_u64() {
+        // Winning charges are 10^18 + 1 ..= 3 * 10^18 - 1.
+        let time = 4 * 10u128.pow(18);
+        let record = 3 * 10u128.pow(36);
+        assert_eq!(winning_charges(time, record), 2 * 10u128.pow(18) - 1);
+    }
 }
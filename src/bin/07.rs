@@ -1,110 +1,156 @@
 advent_of_code::solution!(7);
 
+use advent_of_code::parse;
 use itertools::Itertools;
-use std::cmp::Ordering;
-
-fn score_card(card: char) -> usize {
-    match card {
-        'A' => 14,
-        'K' => 13,
-        'Q' => 12,
-        'J' => 11,
-        'T' => 10,
-        '9' => 9,
-        '8' => 8,
-        '7' => 7,
-        '6' => 6,
-        '5' => 5,
-        '4' => 4,
-        '3' => 3,
-        '2' => 2,
-        'j' => 0,
-        _ => unreachable!(),
-    }
+use std::fmt::Display;
+
+/// The rules of part one: no wildcards, jacks ranked between tens and queens.
+pub const CAMEL_CARDS: Ruleset = Ruleset {
+    order: "23456789TJQKA",
+    wildcards: "",
+    hand_size: 5,
+    categories: FIVE_CARD_CATEGORIES,
+};
+
+/// The rules of part two: jokers stand in for any card but are the weakest on their own.
+pub const JOKERS_WILD: Ruleset = Ruleset {
+    order: "J23456789TQKA",
+    wildcards: "J",
+    hand_size: 5,
+    categories: FIVE_CARD_CATEGORIES,
+};
+
+/// High card, one pair, two pair, three of a kind, full house, four and five of a kind.
+const FIVE_CARD_CATEGORIES: &[&[usize]] = &[
+    &[1, 1, 1, 1, 1],
+    &[2, 1, 1, 1],
+    &[2, 2, 1],
+    &[3, 1, 1],
+    &[3, 2],
+    &[4, 1],
+    &[5],
+];
+
+/// How hands are read and ranked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ruleset {
+    /// Every card label, from weakest to strongest when breaking ties.
+    pub order: &'static str,
+    /// The labels that join whichever group of cards makes the strongest hand.
+    pub wildcards: &'static str,
+    pub hand_size: usize,
+    /// The sizes of the groups of equal cards in each kind of hand, largest group first,
+    /// from the weakest kind of hand to the strongest.
+    pub categories: &'static [&'static [usize]],
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
-    FiveOfAKind = 6,
-    FourOfAKind = 5,
-    FullHouse = 4,
-    ThreeOfAKind = 3,
-    TwoPair = 2,
-    OnePair = 1,
-    HighCard = 0,
+/// Why a hand could not be read under a ruleset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandError {
+    UnknownCard(char),
+    WrongSize {
+        expected: usize,
+        found: usize,
+    },
+    /// The hand's groups of equal cards, largest first, match none of the categories.
+    NoCategory(Vec<usize>),
 }
 
-fn hand_type(cards: &str) -> HandType {
-    let card_hist = cards.chars().counts();
-    let counts = card_hist
-        .iter()
-        .filter_map(|(&k, &v)| if k == 'j' { None } else { Some(v) })
-        .collect::<Vec<_>>();
-    let max_count = *counts.iter().max().unwrap_or(&0);
-    let pairs = counts.iter().filter(|&&v| v == 2).count();
-    let jokers = *card_hist.get(&'j').unwrap_or(&0);
-    match (max_count, pairs, jokers) {
-        (c, _, j) if c + j == 5 => HandType::FiveOfAKind,
-        (c, _, j) if c + j == 4 => HandType::FourOfAKind,
-        (3, p, 0) if p > 0 => HandType::FullHouse,
-        (2, 2, 1) => HandType::FullHouse,
-        (3, _, 0) => HandType::ThreeOfAKind,
-        (2, 1, 1) => HandType::ThreeOfAKind,
-        (1, 0, 2) => HandType::ThreeOfAKind,
-        (2, 2, 0) => HandType::TwoPair,
-        (2, 1, 0) => HandType::OnePair,
-        (1, 0, 1) => HandType::OnePair,
-        _ => HandType::HighCard,
+impl Display for HandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandError::UnknownCard(card) => write!(f, "unknown card {card:?}"),
+            HandError::WrongSize { expected, found } => {
+                write!(f, "expected {expected} cards in a hand, found {found}")
+            }
+            HandError::NoCategory(groups) => {
+                write!(f, "no category for groups of {groups:?} equal cards")
+            }
+        }
     }
 }
 
-fn comp_hands(h1: &str, h2: &str) -> Ordering {
-    match hand_type(h1).cmp(&hand_type(h2)) {
-        Ordering::Equal => h1
+/// A hand ranked under a ruleset. Hands compare first by category, then card by card.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hand {
+    /// The index of the hand's category in [`Ruleset::categories`].
+    pub category: usize,
+    /// The position of each card among the labels of [`Ruleset::order`].
+    pub cards: Vec<usize>,
+}
+
+impl Hand {
+    /// Ranks `cards` by counting how many of each label it holds and sorting those counts
+    /// largest first. Wildcards are added to the largest count, which gives the strongest
+    /// category since categories with larger leading groups always rank higher.
+    pub fn new(cards: &str, rules: &Ruleset) -> Result<Self, HandError> {
+        let labels = rules.order.chars().collect_vec();
+        let cards = cards
             .chars()
-            .zip(h2.chars())
-            .find_map(|(c1, c2)| match score_card(c1).cmp(&score_card(c2)) {
-                Ordering::Equal => None,
-                ord => Some(ord),
+            .map(|card| {
+                let position = labels.iter().position(|&label| label == card);
+                position.ok_or(HandError::UnknownCard(card))
             })
-            .unwrap(),
-        ord => ord,
+            .collect::<Result<Vec<_>, _>>()?;
+        if cards.len() != rules.hand_size {
+            return Err(HandError::WrongSize {
+                expected: rules.hand_size,
+                found: cards.len(),
+            });
+        }
+        let is_wild = |card: usize| rules.wildcards.contains(labels[card]);
+        let wild = cards.iter().filter(|&&card| is_wild(card)).count();
+        let mut groups = cards
+            .iter()
+            .filter(|&&card| !is_wild(card))
+            .counts()
+            .into_values()
+            .sorted_unstable_by(|a, b| b.cmp(a))
+            .collect_vec();
+        match groups.first_mut() {
+            Some(largest) => *largest += wild,
+            None if wild > 0 => groups.push(wild),
+            None => {}
+        }
+        let category = rules
+            .categories
+            .iter()
+            .position(|&category| category == groups)
+            .ok_or(HandError::NoCategory(groups))?;
+        Ok(Self { category, cards })
     }
 }
 
-fn get_cards(input: &str, joker: bool) -> Vec<(String, usize)> {
+fn parse_input(input: &str, rules: &Ruleset) -> Result<Vec<(Hand, u32)>, HandError> {
     input
         .lines()
         .filter(|l| !l.is_empty())
         .map(|l| {
             let (cards, bid) = l.split_once(' ').unwrap();
-            if joker {
-                (cards.replace("J", "j"), bid.parse().unwrap())
-            } else {
-                (cards.to_string(), bid.parse().unwrap())
-            }
+            Ok((Hand::new(cards, rules)?, bid.parse().unwrap()))
         })
         .collect()
 }
 
-fn winnings(cards: Vec<(String, usize)>) -> u32 {
-    cards
-        .iter()
-        .enumerate()
-        .map(|(rank, (_, bid))| ((rank + 1) * bid) as u32)
-        .sum()
+/// Ranks every hand from weakest to strongest and adds up each bid times its rank.
+fn winnings(input: &str, rules: &Ruleset) -> Option<u32> {
+    let mut hands = parse::report(parse_input(input, rules))?;
+    hands.sort_unstable();
+    Some(
+        hands
+            .iter()
+            .zip(1..)
+            .map(|((_, bid), rank)| rank * bid)
+            .sum(),
+    )
 }
 
 pub fn part_one(input: &str) -> Option<u32> {
-    let mut cards = get_cards(input, false);
-    cards.sort_by(|h1, h2| comp_hands(h1.0.as_str(), h2.0.as_str()));
-    Some(winnings(cards))
+    winnings(input, &CAMEL_CARDS)
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
-    let mut cards = get_cards(input, true);
-    cards.sort_by(|h1, h2| comp_hands(h1.0.as_str(), h2.0.as_str()));
-    Some(winnings(cards))
+    winnings(input, &JOKERS_WILD)
 }
 
 #[cfg(test)]
@@ -122,4 +168,69 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(5905));
     }
+
+    #[test]
+    fn test_categories() {
+        let category = |cards| Hand::new(cards, &JOKERS_WILD).unwrap().category;
+        assert_eq!(category("QJJQ2"), 5);
+        assert_eq!(category("JJJJJ"), 6);
+        assert_eq!(category("2345J"), 1);
+        assert_eq!(category("22J33"), 4);
+        assert!(
+            Hand::new("JKKK2", &JOKERS_WILD).unwrap() < Hand::new("QQQQ2", &JOKERS_WILD).unwrap()
+        );
+
+        let two_wild = Ruleset {
+            order: "2Q3J",
+            wildcards: "QJ",
+            hand_size: 3,
+            categories: &[&[1, 1, 1], &[2, 1], &[3]],
+        };
+        assert_eq!(Hand::new("23Q", &two_wild).unwrap().category, 1);
+        assert_eq!(Hand::new("2QJ", &two_wild).unwrap().category, 2);
+        assert_eq!(
+            Hand::new("23", &two_wild),
+            Err(HandError::WrongSize {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            Hand::new("2X3", &two_wild),
+            Err(HandError::UnknownCard('X'))
+        );
+        let no_triples = Ruleset {
+            categories: &[&[1, 1, 1], &[2, 1]],
+            ..two_wild
+        };
+        assert_eq!(
+            Hand::new("22J", &no_triples),
+            Err(HandError::NoCategory(vec![3]))
+        );
+
+        let suits = Ruleset {
+            order: "♣♦♥♠",
+            wildcards: "♥",
+            ..two_wild
+        };
+        assert_eq!(
+            Hand::new("♠♣♥", &suits),
+            Ok(Hand {
+                category: 1,
+                cards: vec![3, 0, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn test_jokers_never_lower_category() {
+        // Every hand of five cards drawn from four labels and the joker.
+        let labels = ['2', '7', 'T', 'A', 'J'];
+        for cards in itertools::repeat_n(labels, 5).multi_cartesian_product() {
+            let cards = cards.into_iter().collect::<String>();
+            let plain = Hand::new(&cards, &CAMEL_CARDS).unwrap();
+            let wild = Hand::new(&cards, &JOKERS_WILD).unwrap();
+            assert!(wild.category >= plain.category, "{cards}");
+        }
+    }
 }
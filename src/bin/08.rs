@@ -1,8 +1,10 @@
 advent_of_code::solution!(8);
 
+use advent_of_code::math::{self, MathError};
+use advent_of_code::parse;
 use itertools::Itertools;
 use std::collections::HashMap;
-use num::integer::lcm;
+use std::fmt::Display;
 
 #[derive(Debug)]
 struct Node<'a> {
@@ -10,7 +12,7 @@ struct Node<'a> {
     right: &'a str,
 }
 
-fn parse_input(input: &str) -> (Vec<char>, HashMap<&str, Node>) {
+fn parse_input(input: &str) -> (Vec<char>, HashMap<&str, Node<'_>>) {
     let (steps, nodes) = input.split_once("\n\n").unwrap();
     let nodes_map = HashMap::from_iter(nodes.lines().map(|line| {
         let parts = line.split_whitespace().collect_vec();
@@ -23,12 +25,7 @@ fn parse_input(input: &str) -> (Vec<char>, HashMap<&str, Node>) {
     (steps.chars().collect_vec(), nodes_map)
 }
 
-fn steps(
-    instructs: &Vec<char>,
-    nodes: &HashMap<&str, Node>,
-    start: &str,
-    end_ends_with: &str,
-) -> u64 {
+fn steps(instructs: &[char], nodes: &HashMap<&str, Node>, start: &str, end_ends_with: &str) -> u64 {
     let mut step_count = 0;
     let mut current_node = start;
 
@@ -55,10 +52,171 @@ pub fn part_one(input: &str) -> Option<u64> {
 
 pub fn part_two(input: &str) -> Option<u64> {
     let (instructs, nodes) = parse_input(input);
-    let start_nodes = nodes.keys().filter(|&name| name.ends_with('A'));
-    let dists_to_end = start_nodes.map(|start| steps(&instructs, &nodes, start, "Z"));
-    let lcm = dists_to_end.fold(1, |acc, dist| lcm(acc, dist));
-    Some(lcm)
+    let ghosts = nodes
+        .keys()
+        .filter(|&name| name.ends_with('A'))
+        .sorted()
+        .map(|&start| (start, Ghost::new(&instructs, &nodes, start)))
+        .collect_vec();
+    if ghosts.is_empty() {
+        eprintln!("no ..A nodes to start from");
+        return None;
+    }
+    let violations = ghosts
+        .iter()
+        .filter_map(|(start, ghost)| Some(format!("{start} {}", ghost.lcm_violation()?)))
+        .collect_vec();
+    if violations.is_empty() {
+        return parse::report(
+            ghosts
+                .iter()
+                .map(|(_, ghost)| ghost.ends[0])
+                .try_fold(1, math::lcm),
+        );
+    }
+    for violation in violations {
+        eprintln!("{violation}, so the cycles are aligned instead of taking their LCM");
+    }
+    parse::report(align(&ghosts))
+}
+
+/// The steps on which a ghost stands on a `..Z` node, found by walking until its
+/// `(node, instruction)` state repeats.
+///
+/// After `offset` steps the ghost loops every `period` steps, so `ends` holds every
+/// end step up to `offset + period` and the rest follow by repetition.
+#[derive(Debug, PartialEq, Eq)]
+struct Ghost {
+    offset: u64,
+    period: u64,
+    ends: Vec<u64>,
+}
+
+impl Ghost {
+    fn new(instructs: &[char], nodes: &HashMap<&str, Node>, start: &str) -> Self {
+        let mut seen = HashMap::new();
+        let mut ends = vec![];
+        let mut current_node = start;
+        for (step, (i, &instruction)) in instructs.iter().enumerate().cycle().enumerate() {
+            let step = step as u64;
+            if let Some(&offset) = seen.get(&(current_node, i)) {
+                return Ghost {
+                    offset,
+                    period: step - offset,
+                    ends,
+                };
+            }
+            seen.insert((current_node, i), step);
+            if step > 0 && current_node.ends_with('Z') {
+                ends.push(step);
+            }
+            let node = nodes.get(current_node).unwrap();
+            current_node = match instruction {
+                'L' => node.left,
+                'R' => node.right,
+                _ => unreachable!(),
+            };
+        }
+        unreachable!();
+    }
+
+    fn at_end(&self, step: u64) -> bool {
+        let step = if step < self.offset {
+            step
+        } else {
+            self.offset + (step - self.offset) % self.period
+        };
+        self.ends.binary_search(&step).is_ok()
+    }
+
+    /// Taking the LCM of the first end steps is only correct if the ghost stands on an
+    /// end node on exactly the multiples of its first end step.
+    fn lcm_violation(&self) -> Option<String> {
+        let Some(&first) = self.ends.first() else {
+            return Some("never reaches an end node".to_string());
+        };
+        if !self.period.is_multiple_of(first) {
+            return Some(format!(
+                "first reaches an end node after {first} steps, but loops every {} steps",
+                self.period
+            ));
+        }
+        let extra = (1..=self.offset + self.period)
+            .find(|&step| self.at_end(step) != step.is_multiple_of(first))?;
+        Some(format!(
+            "first reaches an end node after {first} steps, but {} one after {extra} steps",
+            if self.at_end(extra) {
+                "also reaches"
+            } else {
+                "does not reach"
+            }
+        ))
+    }
+}
+
+/// Finds the first step on which every ghost stands on an end node.
+///
+/// A step before some ghost's cycle starts must be one of that ghost's end steps, so those
+/// are checked first. Any later step must be in the sequence of repeats of one end step
+/// within each ghost's cycle, which [`math::align`] finds.
+fn align(ghosts: &[(&str, Ghost)]) -> Result<u64, GhostError> {
+    if let Some(&(start, _)) = ghosts.iter().find(|(_, ghost)| ghost.ends.is_empty()) {
+        return Err(GhostError::NeverEnds(start.to_string()));
+    }
+    let cycles_from = ghosts.iter().map(|(_, g)| g.offset).max().unwrap_or(0);
+    let before_cycles = ghosts
+        .iter()
+        .flat_map(|(_, ghost)| ghost.ends.iter().copied())
+        .filter(|&step| step < cycles_from)
+        .sorted()
+        .find(|&step| ghosts.iter().all(|(_, ghost)| ghost.at_end(step)));
+    if let Some(step) = before_cycles {
+        return Ok(step);
+    }
+    let alignments = ghosts
+        .iter()
+        .map(|(_, ghost)| {
+            ghost
+                .ends
+                .iter()
+                .filter(|&&step| step >= ghost.offset)
+                .map(|&step| (step, ghost.period))
+                .collect_vec()
+        })
+        .multi_cartesian_product()
+        .map(|sequences| math::align(&sequences))
+        .collect_vec();
+    match alignments.iter().filter_map(|a| a.ok()).min() {
+        Some(step) => Ok(step),
+        None => Err(GhostError::Unaligned(
+            alignments
+                .into_iter()
+                .find_map(Result::err)
+                .unwrap_or(MathError::NoSolution),
+        )),
+    }
+}
+
+/// Why no step puts every ghost on an end node.
+#[derive(Debug, PartialEq, Eq)]
+enum GhostError {
+    /// The ghost starting here never stands on an end node.
+    NeverEnds(String),
+    /// Every ghost reaches end nodes, but never all on the same step.
+    Unaligned(MathError),
+}
+
+impl Display for GhostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GhostError::NeverEnds(start) => {
+                write!(f, "the ghost starting at {start} never reaches an end node")
+            }
+            GhostError::Unaligned(err) => {
+                write!(f, "the ghosts never stand on end nodes together: {err}")
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -68,7 +226,7 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(
-            &advent_of_code::template::read_file("examples", DAY)
+            advent_of_code::template::read_file("examples", DAY)
                 .split_once("\n\n\n")
                 .unwrap()
                 .0,
@@ -79,11 +237,47 @@ mod tests {
     #[test]
     fn test_part_two() {
         let result = part_two(
-            &advent_of_code::template::read_file("examples", DAY)
+            advent_of_code::template::read_file("examples", DAY)
                 .split_once("\n\n\n")
                 .unwrap()
-                .0,
+                .1,
         );
         assert_eq!(result, Some(6));
     }
+
+    #[test]
+    fn test_ghost_cycles() {
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let (instructs, nodes) = parse_input(example.split_once("\n\n\n").unwrap().1);
+        let ghost = Ghost::new(&instructs, &nodes, "22A");
+        assert_eq!(
+            ghost,
+            Ghost {
+                offset: 1,
+                period: 6,
+                ends: vec![3, 6]
+            }
+        );
+        assert_eq!(ghost.lcm_violation(), None);
+        assert!(ghost.at_end(9) && ghost.at_end(12) && !ghost.at_end(10));
+    }
+
+    #[test]
+    fn test_unaligned_cycles() {
+        let offset = "L\n\n11A = (11B, 11B)\n11B = (11Z, 11Z)\n11Z = (11C, 11C)\n11C = (11D, 11D)\n11D = (11Z, 11Z)\n22A = (22Z, 22Z)\n22Z = (22A, 22A)\n";
+        assert_eq!(part_two(offset), Some(5));
+
+        let never = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22B)\n22B = (22Z, 22Z)\n22Z = (22B, 22B)\n";
+        let (instructs, nodes) = parse_input(never);
+        let ghosts = ["11A", "22A"].map(|start| (start, Ghost::new(&instructs, &nodes, start)));
+        assert_eq!(
+            align(&ghosts),
+            Err(GhostError::Unaligned(MathError::NoSolution))
+        );
+    }
+
+    #[test]
+    fn test_no_starts() {
+        assert_eq!(part_two("L\n\n11B = (11Z, 11Z)\n11Z = (11B, 11B)\n"), None);
+    }
 }
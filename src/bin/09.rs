@@ -1,41 +1,40 @@
 advent_of_code::solution!(9);
 
-use itertools::Itertools;
+use advent_of_code::math::{self, MathError};
+use advent_of_code::parse;
 
-fn parse_line(line: &str) -> Vec<i32> {
+fn parse_line(line: &str) -> Vec<i64> {
     line.split_whitespace()
         .map(|w| w.parse().unwrap())
         .collect()
 }
 
-fn next_step(values: &Vec<i32>) -> Vec<i32> {
-    values
-        .iter()
-        .tuple_windows::<(_, _)>()
-        .map(|(a, b)| b - a)
-        .collect()
+/// Sums, over every history, the term `steps` places after its last value, or before its
+/// first value when `steps` is negative.
+fn extrapolate_all(input: &str, steps: i64) -> Result<i64, MathError> {
+    input
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let history = parse_line(l);
+            let n = if steps >= 0 {
+                history.len() as i64 - 1 + steps
+            } else {
+                steps
+            };
+            math::extrapolate(&history, n)
+        })
+        .try_fold(0i64, |total, value| {
+            total.checked_add(value?).ok_or(MathError::Overflow)
+        })
 }
 
-pub fn part_one(input: &str) -> Option<i32> {
-    let values = input.lines().map(|l| {
-        let mut v = vec![parse_line(l)];
-        while v.last().unwrap().iter().any(|&x| x != 0) {
-            v.push(next_step(v.last().unwrap()));
-        }
-        v.iter().rev().fold(0, |a, xs| xs[xs.len() - 1] + a)
-    });
-    Some(values.sum())
+pub fn part_one(input: &str) -> Option<i64> {
+    parse::report(extrapolate_all(input, 1))
 }
 
-pub fn part_two(input: &str) -> Option<i32> {
-    let values = input.lines().map(|l| {
-        let mut v = vec![parse_line(l)];
-        while v.last().unwrap().iter().any(|&x| x != 0) {
-            v.push(next_step(v.last().unwrap()));
-        }
-        v.iter().rev().fold(0, |b, xs| xs[0] - b)
-    });
-    Some(values.sum())
+pub fn part_two(input: &str) -> Option<i64> {
+    parse::report(extrapolate_all(input, -1))
 }
 
 #[cfg(test)]
@@ -53,4 +52,13 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(2));
     }
+
+    #[test]
+    fn test_extrapolate_further() {
+        let input = "10 13 16 21 30 45\n";
+        assert_eq!(extrapolate_all(input, 1), Ok(68));
+        assert_eq!(extrapolate_all(input, 2), Ok(101));
+        assert_eq!(extrapolate_all(input, -1), Ok(5));
+        assert_eq!(extrapolate_all(input, -2), Ok(-4));
+    }
 }
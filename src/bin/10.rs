@@ -1,110 +1,190 @@
-advent_of_code::solution!(10);
-
-use itertools::Itertools;
-
-fn connection_open(tile: &char, direction: (isize, isize)) -> bool {
-    let tile_identity = match tile {
-        // up, down, left, right
-        '|' => [true, true, false, false],
-        '-' => [false, false, true, true],
-        'L' => [true, false, false, true],
-        'J' => [true, false, true, false],
-        '7' => [false, true, true, false],
-        'F' => [false, true, false, true],
-        'S' => [true, true, true, true],
-        _ => [false, false, false, false],
-    };
-    match direction {
-        (-1, 0) => tile_identity[0],
-        (1, 0) => tile_identity[1],
-        (0, -1) => tile_identity[2],
-        (0, 1) => tile_identity[3],
-        (_, _) => unreachable!(),
-    }
+advent_of_code::solution!(10, debug);
+
+use advent_of_code::polygon;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    Up = 0,
+    Right = 1,
+    Down = 2,
+    Left = 3,
 }
 
-fn find_loop(map: &Vec<Vec<char>>, start: (usize, usize)) -> Option<Vec<(usize, usize)>> {
-    let mut visited = vec![start];
-    let (mut y, mut x) = start;
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
 
-    loop {
-        let old_size = visited.len();
-        for &(dy, dx) in &[(1, 0), (-1, 0), (0, -1), (0, 1)] {
-            // Lower bound
-            let maybe_new_y = y as isize + dy;
-            let maybe_new_x = x as isize + dx;
-            if 0 > maybe_new_y || 0 > maybe_new_x {
-                continue;
-            }
-            let new_y = maybe_new_y as usize;
-            let new_x = maybe_new_x as usize;
-            // Upper bound
-            if new_x > map[0].len() || new_y >= map.len() {
-                continue;
-            }
+impl Direction {
+    fn opposite(self) -> Self {
+        DIRECTIONS[(self as usize + 2) % 4]
+    }
+}
 
-            // Check Good connections
-            if connection_open(&map[y][x], (dy, dx))
-                && connection_open(&map[new_y][new_x], (-dy, -dx))
-            {
-                if visited.len() > 2 && new_y == start.0 && new_x == start.1 {
-                    return Some(visited);
-                } else if !visited.contains(&(new_y, new_x)) {
-                    visited.push((new_y, new_x));
-                    y = new_y;
-                    x = new_x;
-                    break;
-                }
-            }
-        }
-        // No progress
-        if old_size == visited.len() {
-            return None;
-        }
+/// The directions a pipe tile connects to, or an empty list for ground and `S`.
+fn connections(tile: char) -> &'static [Direction] {
+    match tile {
+        '|' => &[Direction::Up, Direction::Down],
+        '-' => &[Direction::Right, Direction::Left],
+        'L' => &[Direction::Up, Direction::Right],
+        'J' => &[Direction::Up, Direction::Left],
+        '7' => &[Direction::Down, Direction::Left],
+        'F' => &[Direction::Right, Direction::Down],
+        _ => &[],
     }
 }
 
 pub fn part_one(input: &str) -> Option<usize> {
-    let loop_coords = parse_input(input).1;
-    Some(loop_coords.len() / 2)
+    let maze = PipeMaze::new(input)?;
+    Some(maze.pipe_loop.len() / 2)
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
-    let (map, loop_coords) = parse_input(input);
-    let mut sum = 0;
-    for (y, row) in map.iter().enumerate() {
-        let mut inside_loop = false;
-        for (x, value) in row.iter().enumerate() {
-            if y > 0 && loop_coords.contains(&(y, x)) && connection_open(value, (-1, 0)) {
-                if value == &'S' && !connection_open(&map[y - 1][x], (1, 0)) {
+    let maze = PipeMaze::new(input)?;
+    Some(maze.enclosed())
+}
+
+/// Draws the loop and the tiles it encloses.
+fn debug(input: &str) {
+    if let Some(maze) = PipeMaze::new(input) {
+        println!("{}", maze.render());
+    }
+}
+
+/// A field of pipes with the start tile replaced by the pipe that fits its loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PipeMaze {
+    tiles: Vec<Vec<char>>,
+    start: (usize, usize),
+    /// The tiles of the loop through the start, in order.
+    pipe_loop: Vec<(usize, usize)>,
+}
+
+impl PipeMaze {
+    /// Returns [`None`] if there is no start tile or no two of the pipes it joins lead
+    /// around a loop back to it.
+    fn new(input: &str) -> Option<Self> {
+        let mut tiles = input
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let start = tiles
+            .iter()
+            .enumerate()
+            .find_map(|(row, line)| Some((row, line.iter().position(|&c| c == 'S')?)))?;
+        let joined = DIRECTIONS
+            .into_iter()
+            .filter(|&direction| {
+                step(&tiles, start, direction)
+                    .is_some_and(|(r, c)| connections(tiles[r][c]).contains(&direction.opposite()))
+            })
+            .collect::<Vec<_>>();
+        // More than two pipes may join the start, so try each pair until one closes a loop.
+        let (shape, pipe_loop) = ['|', '-', 'L', 'J', '7', 'F']
+            .into_iter()
+            .find_map(|shape| {
+                let &[first, last] = connections(shape) else {
+                    return None;
+                };
+                if !joined.contains(&first) || !joined.contains(&last) {
+                    return None;
+                }
+                Some((shape, trace(&tiles, start, first, last)?))
+            })?;
+        tiles[start.0][start.1] = shape;
+        Some(Self {
+            tiles,
+            start,
+            pipe_loop,
+        })
+    }
+
+    /// Counts the tiles enclosed by the loop, treating its tile centres as the vertices
+    /// of a polygon and counting the lattice points inside it.
+    fn enclosed(&self) -> usize {
+        let vertices = self
+            .pipe_loop
+            .iter()
+            .map(|&(row, col)| (row as i64, col as i64))
+            .collect::<Vec<_>>();
+        polygon::interior(&vertices) as usize
+    }
+
+    /// Draws the loop with box-drawing characters, enclosed tiles as `I` and the rest as `O`.
+    ///
+    /// A tile is inside when a ray to its left crosses the loop an odd number of times,
+    /// counting only the loop tiles that connect upwards.
+    fn render(&self) -> String {
+        let mut on_loop = vec![vec![false; self.tiles[0].len()]; self.tiles.len()];
+        for &(row, col) in &self.pipe_loop {
+            on_loop[row][col] = true;
+        }
+        let mut lines = vec![];
+        for (row, tiles) in self.tiles.iter().enumerate() {
+            let mut inside = false;
+            let mut line = String::new();
+            for (col, &tile) in tiles.iter().enumerate() {
+                if !on_loop[row][col] {
+                    line.push(if inside { 'I' } else { 'O' });
                     continue;
                 }
-                inside_loop = !inside_loop;
-            } else if !loop_coords.contains(&(y, x)) && inside_loop {
-                sum += 1;
+                if connections(tile).contains(&Direction::Up) {
+                    inside = !inside;
+                }
+                line.push(match tile {
+                    '|' => '│',
+                    '-' => '─',
+                    'L' => '└',
+                    'J' => '┘',
+                    '7' => '┐',
+                    _ => '┌',
+                });
             }
+            lines.push(line);
         }
+        lines.join("\n")
     }
-    Some(sum)
 }
 
-fn parse_input(input: &str) -> (Vec<Vec<char>>, Vec<(usize, usize)>) {
-    let mut start: Option<(usize, usize)> = None;
-    let map = input
-        .lines()
-        .enumerate()
-        .filter_map(|(y, l)| {
-            let row: Vec<char> = l.chars().collect();
-            if let Some(x) = row.iter().position(|&c| c == 'S') {
-                start = Some((y, x));
-            }
-            (!l.is_empty()).then(|| row)
-        })
-        .collect_vec();
-
-    let loop_coords = find_loop(&map, start.unwrap()).unwrap();
+/// Follows the pipes from `start`, leaving towards `first`, and returns the tiles passed
+/// if they lead back into `start` from the `last` side.
+fn trace(
+    tiles: &[Vec<char>],
+    start: (usize, usize),
+    first: Direction,
+    last: Direction,
+) -> Option<Vec<(usize, usize)>> {
+    let mut pipe_loop = vec![start];
+    let (mut pos, mut heading) = (start, first);
+    loop {
+        pos = step(tiles, pos, heading)?;
+        if pos == start {
+            return (heading == last.opposite()).then_some(pipe_loop);
+        }
+        let joins = connections(tiles[pos.0][pos.1]);
+        if !joins.contains(&heading.opposite()) {
+            return None;
+        }
+        pipe_loop.push(pos);
+        heading = *joins.iter().find(|&&d| d != heading.opposite())?;
+    }
+}
 
-    (map, loop_coords)
+fn step(
+    tiles: &[Vec<char>],
+    (row, col): (usize, usize),
+    direction: Direction,
+) -> Option<(usize, usize)> {
+    let (row, col) = match direction {
+        Direction::Up => (row.checked_sub(1)?, col),
+        Direction::Right => (row, col + 1),
+        Direction::Down => (row + 1, col),
+        Direction::Left => (row, col.checked_sub(1)?),
+    };
+    (row < tiles.len() && col < tiles[row].len()).then_some((row, col))
 }
 
 #[cfg(test)]
@@ -114,7 +194,7 @@ mod tests {
     #[test]
     fn test_part_one() {
         let result = part_one(
-            &advent_of_code::template::read_file("examples", DAY)
+            advent_of_code::template::read_file("examples", DAY)
                 .split_once("\n\n")
                 .unwrap()
                 .0,
@@ -125,11 +205,34 @@ mod tests {
     #[test]
     fn test_part_two() {
         let result = part_two(
-            &advent_of_code::template::read_file("examples", DAY)
+            advent_of_code::template::read_file("examples", DAY)
                 .split_once("\n\n")
                 .unwrap()
                 .1,
         );
         assert_eq!(result, Some(10));
     }
+
+    #[test]
+    fn test_pipe_maze() {
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let maze = PipeMaze::new(example.split_once("\n\n").unwrap().0).unwrap();
+        assert_eq!(maze.tiles[maze.start.0][maze.start.1], 'F');
+        assert_eq!(maze.pipe_loop.len(), 16);
+        assert_eq!(maze.enclosed(), 1);
+        assert_eq!(maze.render(), "OO┌┐O\nO┌┘│O\n┌┘I└┐\n│┌──┘\n└┘OOO");
+
+        let squeeze = "..........\n.S------7.\n.|F----7|.\n.||OOOO||.\n.||OOOO||.\n\
+                       .|L-7F-J|.\n.|II||II|.\n.L--JL--J.\n..........\n";
+        let maze = PipeMaze::new(squeeze).unwrap();
+        assert_eq!(maze.enclosed(), 4);
+        assert_eq!(maze.render().matches('I').count(), 4);
+        assert_eq!(PipeMaze::new(".S.\n...\n"), None);
+
+        let crowded = ".|...\n-S-7.\n.|.|.\n.L-J.\n";
+        let maze = PipeMaze::new(crowded).unwrap();
+        assert_eq!(maze.tiles[1][1], 'F');
+        assert_eq!(maze.pipe_loop.len(), 8);
+        assert_eq!(maze.enclosed(), 1);
+    }
 }
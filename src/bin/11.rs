@@ -37,7 +37,7 @@ fn parse_input(input: &str) -> Vec<Vec<bool>> {
         .collect_vec()
 }
 
-fn get_galaxies(universe: &Vec<Vec<bool>>) -> Vec<Vec<usize>> {
+fn get_galaxies(universe: &[Vec<bool>]) -> Vec<Vec<usize>> {
     universe.iter().enumerate().fold(vec![], |mut acc, (y, l)| {
         let mut line_galaxies = l
             .iter()
@@ -53,11 +53,11 @@ fn get_galaxies(universe: &Vec<Vec<bool>>) -> Vec<Vec<usize>> {
 }
 
 fn expand_universe(
-    universe: &Vec<Vec<bool>>,
-    galaxies: &Vec<Vec<usize>>,
+    universe: &[Vec<bool>],
+    galaxies: &[Vec<usize>],
     expansion_value: usize,
 ) -> Vec<Vec<usize>> {
-    let mut new_galaxies = galaxies.clone();
+    let mut new_galaxies = galaxies.to_owned();
     (0..universe.len())
         .filter(|&r| universe[r].iter().all(|&c| !c))
         .rev()
@@ -82,10 +82,10 @@ fn expand_universe(
 }
 
 // https://stackoverflow.com/questions/64498617/how-to-transpose-a-vector-of-vectors-in-rust
-fn transpose(v: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+fn transpose(v: &[Vec<bool>]) -> Vec<Vec<bool>> {
     assert!(!v.is_empty());
     (0..v[0].len())
-        .map(|i| v.iter().map(|inner| inner[i].clone()).collect_vec())
+        .map(|i| v.iter().map(|inner| inner[i]).collect_vec())
         .collect_vec()
 }
 
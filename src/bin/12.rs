@@ -1,101 +1,42 @@
 advent_of_code::solution!(12);
 
-use std::collections::HashMap;
+use advent_of_code::math::MathError;
+use advent_of_code::nonogram::{self, Cell, LineSolver};
+use advent_of_code::parse;
 
-use itertools::Itertools;
+/// How many copies of each record part two joins together.
+const UNFOLD: usize = 5;
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut cache = HashMap::new();
-    let arrangmenet_sum = parse_input(input).iter().fold(0, |acc, (record, nums)| {
-        cache.clear();
-        acc + calc_arrangments(&mut cache, record, nums, None)
-    });
-
-    Some(arrangmenet_sum)
+pub fn part_one(input: &str) -> Option<u64> {
+    parse::report(arrangements(input, 1))
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let mut cache = HashMap::new();
-    let arrangmenet_sum = parse_input(input)
-        .iter()
-        .map(|(record, nums)| {
-            let record_repeat = vec![record.iter().collect::<String>(); 5]
-                .iter()
-                .join("?")
-                .chars()
-                .collect_vec();
-            let nums_repeat = vec![nums.iter().copied().collect_vec(); 5]
-                .iter()
-                .flatten()
-                .copied()
-                .collect_vec();
-            (record_repeat, nums_repeat)
-        })
-        .fold(0, |acc, (record, nums)| {
-            cache.clear();
-            acc + calc_arrangments(&mut cache, &record, &nums, None)
-        });
-
-    Some(arrangmenet_sum)
+pub fn part_two(input: &str) -> Option<u64> {
+    parse::report(arrangements(input, UNFOLD))
 }
 
-fn parse_input(input: &str) -> Vec<(Vec<char>, Vec<usize>)> {
+fn parse_input(input: &str) -> Vec<(Vec<Cell>, Vec<usize>)> {
     input
         .lines()
         .filter(|l| !l.is_empty())
         .map(|l| {
             let (record, rest) = l.split_once(' ').unwrap();
             let nums = rest.split(',').map(|w| w.parse().unwrap()).collect();
-            (record.chars().collect(), nums)
+            let cells = record.chars().map(|c| Cell::try_from(c).unwrap());
+            (cells.collect(), nums)
         })
         .collect()
 }
 
-fn calc_arrangments(
-    cache: &mut HashMap<(usize, usize, usize), usize>,
-    record: &[char],
-    nums: &[usize],
-    confirmed_hash: Option<usize>,
-) -> usize {
-    if record.is_empty() {
-        return match (confirmed_hash, nums.len()) {
-            (None, 0) => 1,
-            (Some(x), 1) if x == nums[0] => 1,
-            _ => 0,
-        };
-    }
-    if confirmed_hash.is_some() && nums.is_empty() {
-        return 0;
-    }
-
-    let key = (record.len(), confirmed_hash.unwrap_or(0), nums.len());
-    if let Some(&x) = cache.get(&key) {
-        return x;
-    }
-    let arrangments = match (record[0], confirmed_hash) {
-        ('.', Some(x)) if x != nums[0] => 0,
-        ('.', Some(_)) => calc_arrangments(cache, &record[1..], &nums[1..], None),
-        ('.', None) => calc_arrangments(cache, &record[1..], nums, None),
-        ('#', Some(_)) => {
-            calc_arrangments(cache, &record[1..], nums, confirmed_hash.map(|x| x + 1))
-        }
-        ('#', None) => calc_arrangments(cache, &record[1..], nums, Some(1)),
-        ('?', Some(x)) => {
-            let mut ans =
-                calc_arrangments(cache, &record[1..], nums, confirmed_hash.map(|x| x + 1));
-            if x == nums[0] {
-                ans += calc_arrangments(cache, &record[1..], &nums[1..], None)
-            }
-            ans
-        }
-        ('?', None) => {
-            calc_arrangments(cache, &record[1..], nums, Some(1))
-                + calc_arrangments(cache, &record[1..], nums, None)
-        }
-        _ => unreachable!(),
-    };
-    cache.insert(key, arrangments);
-    arrangments
+/// Sums the arrangements of every record after unfolding it `factor` times.
+fn arrangements(input: &str, factor: usize) -> Result<u64, MathError> {
+    parse_input(input)
+        .iter()
+        .try_fold(0u64, |total, (record, nums)| {
+            let (record, nums) = nonogram::unfold(record, nums, factor);
+            let count = LineSolver::new(&record, &nums)?.count();
+            total.checked_add(count).ok_or(MathError::Overflow)
+        })
 }
 
 #[cfg(test)]
@@ -113,4 +54,14 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(525152));
     }
+
+    #[test]
+    fn test_arrangements_per_line() {
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let counts = example
+            .lines()
+            .map(|line| arrangements(line, UNFOLD).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [1, 16384, 1, 16, 2500, 506250]);
+    }
 }
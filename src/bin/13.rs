@@ -4,18 +4,14 @@ advent_of_code::solution!(13);
 
 pub fn part_one(input: &str) -> Option<usize> {
     let grids = parse_input(input);
-    let reflections_sum = grids
-        .iter()
-        .fold(0, |acc, grid| acc + reflections(&grid, 0));
+    let reflections_sum = grids.iter().fold(0, |acc, grid| acc + reflections(grid, 0));
 
     Some(reflections_sum)
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
     let grids = parse_input(input);
-    let reflections_sum = grids
-        .iter()
-        .fold(0, |acc, grid| acc + reflections(&grid, 1));
+    let reflections_sum = grids.iter().fold(0, |acc, grid| acc + reflections(grid, 1));
 
     Some(reflections_sum)
 }
@@ -31,17 +27,15 @@ fn parse_input(input: &str) -> Vec<Vec<Vec<bool>>> {
         .collect()
 }
 
-fn reflections(grid: &Vec<Vec<bool>>, max_smudges: usize) -> usize {
+fn reflections(grid: &[Vec<bool>], max_smudges: usize) -> usize {
     if let Some(h_reflections) = horizontal_reflections(grid, max_smudges) {
         h_reflections * 100
-    } else if let Some(v_reflections) = horizontal_reflections(&transpose(&grid), max_smudges) {
-        v_reflections
     } else {
-        0
+        horizontal_reflections(&transpose(grid), max_smudges).unwrap_or_default()
     }
 }
 
-fn horizontal_reflections(grid: &Vec<Vec<bool>>, max_smudges: usize) -> Option<usize> {
+fn horizontal_reflections(grid: &[Vec<bool>], max_smudges: usize) -> Option<usize> {
     'row: for y in 0..grid.len() - 1 {
         let mut smudge_count = diff_row(&grid[y], &grid[y + 1]);
         if smudge_count <= max_smudges {
@@ -62,15 +56,15 @@ fn horizontal_reflections(grid: &Vec<Vec<bool>>, max_smudges: usize) -> Option<u
     None
 }
 
-fn diff_row(row1: &Vec<bool>, row2: &Vec<bool>) -> usize {
+fn diff_row(row1: &[bool], row2: &[bool]) -> usize {
     zip(row1, row2).filter(|&(v1, v2)| v1 ^ v2).count()
 }
 
 // https://stackoverflow.com/questions/64498617/how-to-transpose-a-vector-of-vectors-in-rust
-fn transpose(v: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+fn transpose(v: &[Vec<bool>]) -> Vec<Vec<bool>> {
     assert!(!v.is_empty());
     (0..v[0].len())
-        .map(|i| v.iter().map(|inner| inner[i].clone()).collect())
+        .map(|i| v.iter().map(|inner| inner[i]).collect())
         .collect()
 }
 
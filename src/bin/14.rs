@@ -2,77 +2,144 @@ use std::collections::HashMap;
 
 advent_of_code::solution!(14);
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut grid = parse_input(input);
-    roll_up(&mut grid);
+const SPINS: usize = 1_000_000_000;
 
-    Some(score_grid(&grid))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    North = 0,
+    West = 1,
+    South = 2,
+    East = 3,
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let mut cache = HashMap::<Vec<Vec<char>>, usize>::new();
-    let mut grid = parse_input(input);
+pub fn part_one(input: &str) -> Option<usize> {
+    let mut platform = Platform::new(input);
+    platform.tilt(Direction::North);
+    Some(platform.north_load())
+}
 
-    for step in 1..=1_000_000_000 {
-        for _ in 0..4 {
-            roll_up(&mut grid);
-            rotate_left(&mut grid);
-        }
-        if let Some(seen) = cache.insert(grid.clone(), step) {
-            if (1000000000 - step) % (step - seen) == 0 {
-                break;
-            }
+pub fn part_two(input: &str) -> Option<usize> {
+    let mut platform = Platform::new(input);
+    let mut seen = HashMap::new();
+    // The north load after each number of spins so far.
+    let mut loads = vec![];
+    for spin in 0..SPINS {
+        if let Some(first) = seen.insert(platform.rocks.clone(), spin) {
+            return Some(loads[first + (SPINS - first) % (spin - first)]);
         }
+        loads.push(platform.north_load());
+        platform.spin();
     }
-
-    Some(score_grid(&grid))
+    Some(platform.north_load())
 }
 
-fn parse_input(input: &str) -> Vec<Vec<char>> {
-    input
-        .lines()
-        .filter(|l| !l.is_empty())
-        .map(|l| l.chars().collect())
-        .collect()
+/// The rounded rocks on a platform, as sorted cell indexes in row-major order.
+///
+/// Cube rocks split each row and column into segments. For every direction, `stops`
+/// holds the cell at the far end of each cell's segment, where rounded rocks in that
+/// segment pile up when the platform is tilted that way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Platform {
+    height: usize,
+    width: usize,
+    rocks: Vec<usize>,
+    stops: [Vec<usize>; 4],
+    /// Scratch space counting the rocks piling against each stop during a tilt.
+    piles: Vec<usize>,
 }
 
-fn score_grid(grid: &Vec<Vec<char>>) -> usize {
-    (0..grid.len())
-        .map(|row| {
-            (0..grid[0].len())
-                .filter(|&col| grid[row][col] == 'O')
-                .map(|_| grid.len() - row)
-                .sum::<usize>()
-        })
-        .sum()
-}
+impl Platform {
+    fn new(input: &str) -> Self {
+        let lines = input.lines().filter(|l| !l.is_empty()).collect::<Vec<_>>();
+        let (height, width) = (lines.len(), lines[0].len());
+        let cells = lines.iter().flat_map(|l| l.chars()).collect::<Vec<_>>();
+        let rocks = (0..cells.len()).filter(|&i| cells[i] == 'O').collect();
 
-fn roll_up(grid: &mut Vec<Vec<char>>) {
-    'looping: loop {
-        for row in 0..grid.len() - 1 {
-            for col in 0..grid[0].len() {
-                if grid[row + 1][col] == 'O' && grid[row][col] == '.' {
-                    grid[row][col] = 'O';
-                    grid[row + 1][col] = '.';
-                    continue 'looping;
+        let stops = [
+            Direction::North,
+            Direction::West,
+            Direction::South,
+            Direction::East,
+        ]
+        .map(|direction| {
+            let mut stops = vec![usize::MAX; cells.len()];
+            // Visit cells so that each one's neighbour in `direction` is already done.
+            let order: Box<dyn Iterator<Item = usize>> = match direction {
+                Direction::North | Direction::West => Box::new(0..cells.len()),
+                Direction::South | Direction::East => Box::new((0..cells.len()).rev()),
+            };
+            for i in order {
+                if cells[i] == '#' {
+                    continue;
                 }
+                stops[i] = match neighbour(height, width, i, direction) {
+                    Some(next) if cells[next] != '#' => stops[next],
+                    _ => i,
+                };
             }
+            stops
+        });
+        Self {
+            height,
+            width,
+            rocks,
+            stops,
+            piles: vec![0; cells.len()],
         }
-        break;
     }
-}
 
-fn rotate_left<T>(grid: &mut Vec<Vec<T>>)
-where
-    T: Clone,
-    T: Copy,
-{
-    let grid_clone = grid.clone();
-    for row in 0..grid.len() {
-        for col in 0..grid[0].len() {
-            let new_coords = (col, grid.len() - 1 - row);
-            grid[new_coords.0][new_coords.1] = grid_clone[row][col];
+    /// Rolls every rounded rock as far as it goes in `direction`.
+    fn tilt(&mut self, direction: Direction) {
+        let stops = &self.stops[direction as usize];
+        let mut used = vec![];
+        for &rock in &self.rocks {
+            if self.piles[stops[rock]] == 0 {
+                used.push(stops[rock]);
+            }
+            self.piles[stops[rock]] += 1;
         }
+        let away = match direction {
+            Direction::North => self.width as isize,
+            Direction::West => 1,
+            Direction::South => -(self.width as isize),
+            Direction::East => -1,
+        };
+        self.rocks.clear();
+        for stop in used {
+            let count = std::mem::take(&mut self.piles[stop]);
+            self.rocks
+                .extend((0..count as isize).map(|i| (stop as isize + i * away) as usize));
+        }
+        self.rocks.sort_unstable();
+    }
+
+    /// Tilts north, west, south then east.
+    fn spin(&mut self) {
+        for direction in [
+            Direction::North,
+            Direction::West,
+            Direction::South,
+            Direction::East,
+        ] {
+            self.tilt(direction);
+        }
+    }
+
+    fn north_load(&self) -> usize {
+        self.rocks
+            .iter()
+            .map(|&rock| self.height - rock / self.width)
+            .sum()
+    }
+}
+
+fn neighbour(height: usize, width: usize, i: usize, direction: Direction) -> Option<usize> {
+    let (row, col) = (i / width, i % width);
+    match direction {
+        Direction::North => row.checked_sub(1).map(|r| r * width + col),
+        Direction::West => col.checked_sub(1).map(|c| row * width + c),
+        Direction::South => (row + 1 < height).then_some(i + width),
+        Direction::East => (col + 1 < width).then_some(i + 1),
     }
 }
 
@@ -91,4 +158,29 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(64));
     }
+
+    #[test]
+    fn test_spin() {
+        let mut platform = Platform::new(&advent_of_code::template::read_file("examples", DAY));
+        platform.spin();
+        let expected = Platform::new(
+            ".....#....\n....#...O#\n...OO##...\n.OO#......\n.....OOO#.\n\
+             .O#...O#.#\n....O#....\n......OOOO\n#...O###..\n#..OO#....\n",
+        );
+        assert_eq!(platform.rocks, expected.rocks);
+
+        platform.tilt(Direction::East);
+        assert_eq!(platform.rocks, expected.rocks);
+    }
+
+    #[test]
+    fn test_tilt_north() {
+        let mut platform = Platform::new(&advent_of_code::template::read_file("examples", DAY));
+        platform.tilt(Direction::North);
+        let expected = Platform::new(
+            "OOOO.#.O..\nOO..#....#\nOO..O##..O\nO..#.OO...\n........#.\n\
+             ..#....#.#\n..O..#.O.O\n..O.......\n#....###..\n#....#....\n",
+        );
+        assert_eq!(platform.rocks, expected.rocks);
+    }
 }
@@ -1,7 +1,16 @@
-advent_of_code::solution!(16);
+use itertools::Itertools;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::DiGraph;
+use std::collections::HashMap;
+use std::thread;
+
+advent_of_code::solution!(16, debug);
 
 type Beam = (usize, usize, Direction);
 
+/// A set of tiles, one bit per tile in row-major order.
+type Tiles = Vec<u64>;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum Direction {
     Up = 0,
@@ -12,34 +21,46 @@ enum Direction {
 
 pub fn part_one(input: &str) -> Option<usize> {
     let grid = parse_input(input);
-    let coverage = beam_coverage(&grid, (0, 0, Direction::Right));
-    Some(coverage)
+    let beams = BeamGraph::new(&grid);
+    Some(count(&beams.energised((0, 0, Direction::Right))))
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
     let grid = parse_input(input);
-    let mut starting_positions: Vec<Beam> = (0..grid.len())
+    let beams = BeamGraph::new(&grid);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    max_energised(&beams, &edge_starts(&grid), threads).map(|(best, _)| best)
+}
+
+/// Draws the tiles energised from the top-left corner and from the best edge start.
+fn debug(input: &str) {
+    let grid = parse_input(input);
+    let beams = BeamGraph::new(&grid);
+    println!(
+        "{}",
+        render(&grid, &beams.energised((0, 0, Direction::Right)))
+    );
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if let Some((_, start)) = max_energised(&beams, &edge_starts(&grid), threads) {
+        println!("{start:?}\n{}", render(&grid, &beams.energised(start)));
+    }
+}
+
+/// Every beam entering the grid from one of its edges.
+fn edge_starts(grid: &[Vec<char>]) -> Vec<Beam> {
+    let (height, width) = (grid.len(), grid[0].len());
+    (0..height)
         .flat_map(|row| {
             [
                 (row, 0, Direction::Right),
-                (row, grid[0].len() - 1, Direction::Left),
+                (row, width - 1, Direction::Left),
             ]
         })
-        .collect();
-    starting_positions.extend((0..grid[0].len()).flat_map(|col| {
-        [
-            (0, col, Direction::Down),
-            (grid.len() - 1, col, Direction::Up),
-        ]
-    }));
-
-    Some(
-        starting_positions
-            .iter()
-            .map(|&start| beam_coverage(&grid, start))
-            .max()
-            .unwrap(),
-    )
+        .chain(
+            (0..width)
+                .flat_map(|col| [(0, col, Direction::Down), (height - 1, col, Direction::Up)]),
+        )
+        .collect_vec()
 }
 
 fn parse_input(input: &str) -> Vec<Vec<char>> {
@@ -50,53 +71,179 @@ fn parse_input(input: &str) -> Vec<Vec<char>> {
         .collect()
 }
 
-fn beam_coverage(grid: &Vec<Vec<char>>, start: Beam) -> usize {
-    let mut seen: Vec<Vec<Vec<Direction>>> = vec![vec![vec![]; grid[0].len()]; grid.len()];
-    let mut active_beams: Vec<Beam> = vec![start];
+/// Returns the most tiles energised by any of `starts`, using up to `threads` threads.
+fn max_energised(beams: &BeamGraph, starts: &[Beam], threads: usize) -> Option<(usize, Beam)> {
+    let best = |chunk: &[Beam]| {
+        chunk
+            .iter()
+            .map(|&start| (count(&beams.energised(start)), start))
+            .max()
+    };
+    if threads <= 1 {
+        return best(starts);
+    }
+    thread::scope(|scope| {
+        starts
+            .chunks(starts.len().div_ceil(threads).max(1))
+            .map(|chunk| scope.spawn(move || best(chunk)))
+            .collect_vec()
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .max()
+    })
+}
+
+/// The tiles lit from each splitter, memoised per strongly connected component of splitters.
+struct BeamGraph<'a> {
+    grid: &'a [Vec<char>],
+    /// The component of each splitter, by position.
+    components: HashMap<(usize, usize), usize>,
+    /// Every tile lit once a beam is split by a splitter in each component.
+    lit: Vec<Tiles>,
+}
+
+impl<'a> BeamGraph<'a> {
+    fn new(grid: &'a [Vec<char>]) -> Self {
+        let mut graph = DiGraph::<(usize, usize), ()>::new();
+        let mut nodes = HashMap::new();
+        for (row, col) in (0..grid.len()).cartesian_product(0..grid[0].len()) {
+            if matches!(grid[row][col], '|' | '-') {
+                nodes.insert((row, col), graph.add_node((row, col)));
+            }
+        }
+        let mut local = HashMap::new();
+        for (&pos, &node) in &nodes {
+            let mut tiles = empty(grid);
+            set(&mut tiles, grid, pos);
+            for beam in split(grid, pos) {
+                let (run, next) = trace(grid, beam);
+                union(&mut tiles, &run);
+                if let Some(next) = next {
+                    graph.add_edge(node, nodes[&next], ());
+                }
+            }
+            local.insert(node, tiles);
+        }
 
-    while !active_beams.is_empty() {
-        let mut new_beams: Vec<Beam> = vec![];
-        for (row, col, direction) in active_beams {
-            if row >= grid.len() || col >= grid[0].len() || seen[row][col].contains(&direction) {
-                continue;
+        // Tarjan's algorithm yields components in reverse topological order, so every
+        // component reached from one is already built.
+        let mut components = HashMap::new();
+        let mut lit = vec![];
+        for (i, component) in tarjan_scc(&graph).into_iter().enumerate() {
+            let mut tiles = empty(grid);
+            for &node in &component {
+                components.insert(node, i);
             }
-            seen[row][col].push(direction);
-            match (grid[row][col], direction) {
-                ('-', Direction::Up | Direction::Down) => new_beams.extend([
-                    step_beam(row, col, Direction::Left),
-                    step_beam(row, col, Direction::Right),
-                ]),
-                ('|', Direction::Left | Direction::Right) => new_beams.extend([
-                    step_beam(row, col, Direction::Up),
-                    step_beam(row, col, Direction::Down),
-                ]),
-                ('/', Direction::Up) => new_beams.push(step_beam(row, col, Direction::Right)),
-                ('/', Direction::Down) => new_beams.push(step_beam(row, col, Direction::Left)),
-                ('/', Direction::Left) => new_beams.push(step_beam(row, col, Direction::Down)),
-                ('/', Direction::Right) => new_beams.push(step_beam(row, col, Direction::Up)),
-                ('\\', Direction::Up) => new_beams.push(step_beam(row, col, Direction::Left)),
-                ('\\', Direction::Down) => new_beams.push(step_beam(row, col, Direction::Right)),
-                ('\\', Direction::Left) => new_beams.push(step_beam(row, col, Direction::Up)),
-                ('\\', Direction::Right) => new_beams.push(step_beam(row, col, Direction::Down)),
-                _ => new_beams.push(step_beam(row, col, direction)),
+            for &node in &component {
+                union(&mut tiles, &local[&node]);
+                for next in graph.neighbors(node) {
+                    if components[&next] != i {
+                        union(&mut tiles, &lit[components[&next]]);
+                    }
+                }
             }
+            lit.push(tiles);
+        }
+        Self {
+            grid,
+            components: nodes
+                .iter()
+                .map(|(&pos, node)| (pos, components[node]))
+                .collect(),
+            lit,
+        }
+    }
+
+    /// Returns the tiles energised by a beam entering at `start`.
+    fn energised(&self, start: Beam) -> Tiles {
+        let (mut tiles, splitter) = trace(self.grid, Some(start));
+        if let Some(splitter) = splitter {
+            union(&mut tiles, &self.lit[self.components[&splitter]]);
         }
-        active_beams = new_beams;
+        tiles
     }
+}
+
+/// Follows a beam until it leaves the grid or splits, returning the tiles lit and the splitter.
+fn trace(grid: &[Vec<char>], beam: Option<Beam>) -> (Tiles, Option<(usize, usize)>) {
+    let mut tiles = empty(grid);
+    let mut seen = vec![0u8; grid.len() * grid[0].len()];
+    let mut beam = beam;
+    while let Some((row, col, direction)) = beam {
+        let mask = &mut seen[row * grid[0].len() + col];
+        if *mask & 1 << direction as u8 != 0 {
+            break;
+        }
+        *mask |= 1 << direction as u8;
+        set(&mut tiles, grid, (row, col));
+        let direction = match (grid[row][col], direction) {
+            ('-', Direction::Up | Direction::Down) | ('|', Direction::Left | Direction::Right) => {
+                return (tiles, Some((row, col)));
+            }
+            ('/', Direction::Up) | ('\\', Direction::Down) => Direction::Right,
+            ('/', Direction::Down) | ('\\', Direction::Up) => Direction::Left,
+            ('/', Direction::Left) | ('\\', Direction::Right) => Direction::Down,
+            ('/', Direction::Right) | ('\\', Direction::Left) => Direction::Up,
+            _ => direction,
+        };
+        beam = step_beam(grid, row, col, direction);
+    }
+    (tiles, None)
+}
+
+/// The two beams sent out by a splitter hit side-on.
+fn split(grid: &[Vec<char>], (row, col): (usize, usize)) -> [Option<Beam>; 2] {
+    let directions = match grid[row][col] {
+        '-' => [Direction::Left, Direction::Right],
+        _ => [Direction::Up, Direction::Down],
+    };
+    directions.map(|direction| step_beam(grid, row, col, direction))
+}
+
+fn step_beam(grid: &[Vec<char>], row: usize, col: usize, direction: Direction) -> Option<Beam> {
+    let (row, col) = match direction {
+        Direction::Up => (row.checked_sub(1)?, col),
+        Direction::Right => (row, col + 1),
+        Direction::Down => (row + 1, col),
+        Direction::Left => (row, col.checked_sub(1)?),
+    };
+    (row < grid.len() && col < grid[0].len()).then_some((row, col, direction))
+}
+
+fn empty(grid: &[Vec<char>]) -> Tiles {
+    vec![0; (grid.len() * grid[0].len()).div_ceil(64)]
+}
 
-    seen.iter()
-        .flat_map(|row| row)
-        .filter(|point| !point.is_empty())
-        .count()
+fn set(tiles: &mut Tiles, grid: &[Vec<char>], (row, col): (usize, usize)) {
+    let i = row * grid[0].len() + col;
+    tiles[i / 64] |= 1 << (i % 64);
 }
 
-fn step_beam(row: usize, col: usize, direction: Direction) -> (usize, usize, Direction) {
-    let (delta_vert, delta_horz) = [(-1, 0), (0, 1), (1, 0), (0, -1)][direction as usize];
-    (
-        (row as isize + delta_vert) as usize,
-        (col as isize + delta_horz) as usize,
-        direction,
-    )
+fn union(tiles: &mut Tiles, other: &Tiles) {
+    tiles.iter_mut().zip(other).for_each(|(a, b)| *a |= b);
+}
+
+fn count(tiles: &Tiles) -> usize {
+    tiles.iter().map(|word| word.count_ones() as usize).sum()
+}
+
+/// Draws energised tiles as `#` and the rest as `.`, as in the puzzle text.
+fn render(grid: &[Vec<char>], tiles: &Tiles) -> String {
+    let width = grid[0].len();
+    (0..grid.len())
+        .map(|row| {
+            (0..width)
+                .map(|col| {
+                    let i = row * width + col;
+                    if tiles[i / 64] & 1 << (i % 64) != 0 {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -114,4 +261,34 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(51));
     }
+
+    #[test]
+    fn test_render_and_threads() {
+        let grid = parse_input(&advent_of_code::template::read_file("examples", DAY));
+        let beams = BeamGraph::new(&grid);
+        let tiles = beams.energised((0, 0, Direction::Right));
+        assert_eq!(
+            render(&grid, &tiles),
+            "######....\n.#...#....\n.#...#####\n.#...##...\n.#...##...\n\
+             .#...##...\n.#..####..\n########..\n.#######..\n.#...#.#.."
+        );
+
+        let starts = (0..grid.len())
+            .map(|row| (row, 0, Direction::Right))
+            .collect_vec();
+        assert_eq!(
+            max_energised(&beams, &starts, 1),
+            max_energised(&beams, &starts, 4)
+        );
+        assert_eq!(max_energised(&beams, &[], 4), None);
+    }
+
+    #[test]
+    fn test_splitter_loop() {
+        // The beam is split into a loop between the two splitters, lighting both rows.
+        let grid = parse_input(".|.\\\n.\\-/\n....\n");
+        let beams = BeamGraph::new(&grid);
+        let tiles = beams.energised((0, 0, Direction::Right));
+        assert_eq!(render(&grid, &tiles), "####\n.###\n....");
+    }
 }
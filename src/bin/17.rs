@@ -1,24 +1,47 @@
-use std::collections::{BinaryHeap, HashMap};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 
-advent_of_code::solution!(17);
+advent_of_code::solution!(17, debug);
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Direction {
-    None = 0,
-    Up = 1,
-    Right = 2,
-    Down = 3,
-    Left = 4,
+    Up = 0,
+    Right = 1,
+    Down = 2,
+    Left = 3,
 }
 
-pub fn part_one(input: &str) -> Option<i32> {
-    let blocks = parse_input(input);
-    Some(path_find(blocks, 1, 3))
+/// How far a crucible must, and may, move in a straight line before it turns.
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    min_step: usize,
+    max_step: usize,
 }
 
-pub fn part_two(input: &str) -> Option<i32> {
-    let blocks = parse_input(input);
-    Some(path_find(blocks, 4, 10))
+const CRUCIBLE: Rules = Rules {
+    min_step: 1,
+    max_step: 3,
+};
+
+const ULTRA_CRUCIBLE: Rules = Rules {
+    min_step: 4,
+    max_step: 10,
+};
+
+/// The cheapest route found by [`path_find`], as each cell entered and the direction
+/// it was entered in.
+#[derive(Debug, PartialEq, Eq)]
+struct Path {
+    heat_loss: u32,
+    moves: Vec<((usize, usize), Direction)>,
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    solve(&parse_input(input), CRUCIBLE)
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    solve(&parse_input(input), ULTRA_CRUCIBLE)
 }
 
 fn parse_input(input: &str) -> Vec<Vec<u32>> {
@@ -29,72 +52,140 @@ fn parse_input(input: &str) -> Vec<Vec<u32>> {
         .collect()
 }
 
-// dijkstras for path finding
-fn path_find(grid: Vec<Vec<u32>>, minstep: isize, maxstep: isize) -> i32 {
-    let mut dists = HashMap::new();
-    let mut queue = BinaryHeap::from_iter([(0, (0, 0, Direction::None))]);
-    while let Some((cost, (row, col, direction))) = queue.pop() {
-        if (row, col) == (grid.len() - 1, grid[0].len() - 1) {
-            return -cost;
+fn solve(grid: &[Vec<u32>], rules: Rules) -> Option<u32> {
+    let goal = (grid.len() - 1, grid[0].len() - 1);
+    path_find(grid, rules, (0, 0), goal).map(|path| path.heat_loss)
+}
+
+/// Draws the path each part finds over the grid.
+fn debug(input: &str) {
+    let grid = parse_input(input);
+    let goal = (grid.len() - 1, grid[0].len() - 1);
+    for rules in [CRUCIBLE, ULTRA_CRUCIBLE] {
+        if let Some(path) = path_find(&grid, rules, (0, 0), goal) {
+            println!("{}", render(&grid, &path));
+        }
+    }
+}
+
+/// Finds the path from `start` to `goal` that loses the least heat with Dijkstra's algorithm.
+///
+/// A state is a cell and the axis of the last move into it, since the next move must
+/// turn onto the other axis. States are indexed as `(row * width + col) * 2 + axis` into
+/// dense tables, with axis `0` for vertical moves and `1` for horizontal ones.
+fn path_find(
+    grid: &[Vec<u32>],
+    rules: Rules,
+    start: (usize, usize),
+    goal: (usize, usize),
+) -> Option<Path> {
+    let (height, width) = (grid.len(), grid[0].len());
+    let cell = |state: usize| (state / 2 / width, state / 2 % width);
+    let mut dists = vec![u32::MAX; height * width * 2];
+    let mut prev = vec![None; height * width * 2];
+    let mut queue = BinaryHeap::new();
+    for axis in 0..2 {
+        let state = (start.0 * width + start.1) * 2 + axis;
+        dists[state] = 0;
+        queue.push(Reverse((0, state)));
+    }
+    while let Some(Reverse((cost, state))) = queue.pop() {
+        if cell(state) == goal {
+            return Some(Path {
+                heat_loss: cost,
+                moves: trace_moves(&prev, state, cell),
+            });
         }
-        if dists
-            .get(&(row, col, direction))
-            .is_some_and(|&c| -cost > c)
-        {
+        if cost > dists[state] {
             continue;
         }
-        for new_direction in [
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-        ] {
-            if direction == new_direction || direction_opposites(direction, new_direction) {
-                continue;
-            }
-            let mut next_cost = -cost;
-            for dist in 1..=maxstep {
-                let (new_row, new_col, _) = step_pos(row, col, new_direction, dist);
-                if new_row >= grid.len() || new_col >= grid[0].len() {
-                    continue;
-                }
-                next_cost += (grid[new_row][new_col]) as i32;
-                if dist < minstep {
+        let turns = match state % 2 {
+            0 => [Direction::Left, Direction::Right],
+            _ => [Direction::Up, Direction::Down],
+        };
+        for direction in turns {
+            let mut next_cost = cost;
+            let mut pos = cell(state);
+            for dist in 1..=rules.max_step {
+                let Some(next) = step(pos, direction, height, width) else {
+                    break;
+                };
+                pos = next;
+                next_cost += grid[pos.0][pos.1];
+                if dist < rules.min_step {
                     continue;
                 }
-                let key = (new_row, new_col, new_direction);
-                if next_cost < *dists.get(&key).unwrap_or(&i32::MAX) {
-                    dists.insert(key, next_cost);
-                    queue.push((-next_cost, key));
+                let next_state = (pos.0 * width + pos.1) * 2 + (1 - state % 2);
+                if next_cost < dists[next_state] {
+                    dists[next_state] = next_cost;
+                    prev[next_state] = Some(state);
+                    queue.push(Reverse((next_cost, next_state)));
                 }
             }
         }
     }
-    unreachable!()
+    None
 }
 
-fn direction_opposites(dir1: Direction, dir2: Direction) -> bool {
-    match (dir1, dir2) {
-        (Direction::Up, Direction::Down)
-        | (Direction::Down, Direction::Up)
-        | (Direction::Left, Direction::Right)
-        | (Direction::Right, Direction::Left) => true,
-        _ => false,
+/// Follows `prev` back from `state`, expanding each straight run into the cells it entered.
+fn trace_moves(
+    prev: &[Option<usize>],
+    mut state: usize,
+    cell: impl Fn(usize) -> (usize, usize),
+) -> Vec<((usize, usize), Direction)> {
+    let mut moves = vec![];
+    while let Some(from) = prev[state] {
+        let (mut pos, to) = (cell(from), cell(state));
+        let direction = match (to.0.cmp(&pos.0), to.1.cmp(&pos.1)) {
+            (Ordering::Less, _) => Direction::Up,
+            (Ordering::Greater, _) => Direction::Down,
+            (_, Ordering::Less) => Direction::Left,
+            _ => Direction::Right,
+        };
+        let mut run = vec![];
+        while pos != to {
+            pos = step(pos, direction, usize::MAX, usize::MAX).unwrap();
+            run.push((pos, direction));
+        }
+        moves.splice(0..0, run);
+        state = from;
     }
+    moves
 }
 
-fn step_pos(
-    row: usize,
-    col: usize,
+fn step(
+    (row, col): (usize, usize),
     direction: Direction,
-    dist: isize,
-) -> (usize, usize, Direction) {
-    let (delta_vert, delta_horz) = [(0, 0), (-1, 0), (0, 1), (1, 0), (0, -1)][direction as usize];
-    (
-        (row as isize + delta_vert * dist) as usize,
-        (col as isize + delta_horz * dist) as usize,
-        direction,
-    )
+    height: usize,
+    width: usize,
+) -> Option<(usize, usize)> {
+    let (row, col) = match direction {
+        Direction::Up => (row.checked_sub(1)?, col),
+        Direction::Right => (row, col + 1),
+        Direction::Down => (row + 1, col),
+        Direction::Left => (row, col.checked_sub(1)?),
+    };
+    (row < height && col < width).then_some((row, col))
+}
+
+/// Draws the grid with an arrow on every cell the path enters, as in the puzzle text.
+fn render(grid: &[Vec<u32>], path: &Path) -> String {
+    let mut cells = grid
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&d| char::from_digit(d, 10).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for &((row, col), direction) in &path.moves {
+        cells[row][col] = ['^', '>', 'v', '<'][direction as usize];
+    }
+    cells
+        .into_iter()
+        .map(|row| row.into_iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -111,5 +202,31 @@ mod tests {
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(94));
+
+        let unfortunate = "111111111111\n999999999991\n999999999991\n999999999991\n999999999991\n";
+        assert_eq!(part_two(unfortunate), Some(71));
+    }
+
+    #[test]
+    fn test_path() {
+        let grid = parse_input(&advent_of_code::template::read_file("examples", DAY));
+        let goal = (grid.len() - 1, grid[0].len() - 1);
+        let path = path_find(&grid, CRUCIBLE, (0, 0), goal).unwrap();
+        let entered = path.moves.iter().map(|&((r, c), _)| grid[r][c]);
+        assert_eq!(entered.sum::<u32>(), path.heat_loss);
+        assert_eq!(path.moves.last().unwrap().0, goal);
+
+        let rendered = render(&grid, &path);
+        assert_eq!(rendered.lines().count(), grid.len());
+        assert!(rendered.starts_with('2'));
+        assert_eq!(
+            rendered.matches(['^', '>', 'v', '<']).count(),
+            path.moves.len()
+        );
+
+        let back = path_find(&grid, CRUCIBLE, goal, (0, 0)).unwrap();
+        assert_eq!(back.moves.last().unwrap().0, (0, 0));
+        let still = path_find(&grid, CRUCIBLE, (3, 3), (3, 3)).unwrap();
+        assert_eq!((still.heat_loss, still.moves.len()), (0, 0));
     }
 }
@@ -1,90 +1,192 @@
-advent_of_code::solution!(18);
+advent_of_code::solution!(18, debug);
+
+use advent_of_code::parse::{self, ParseError, ParseErrorKind};
+use advent_of_code::polygon;
+use advent_of_code::template;
+
+/// The longest side, in pixels, of the images written with `--debug`.
+const MAX_IMAGE_SIZE: i64 = 1024;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Direction {
-    _None = 0,
-    Up = 1,
-    Right = 2,
-    Down = 3,
-    Left = 4,
+    Up = 0,
+    Right = 1,
+    Down = 2,
+    Left = 3,
 }
 
-pub fn part_one(input: &str) -> Option<isize> {
-    let steps = parse_input(input)
-        .iter()
-        .map(|&(dir, dist, _)| (dir, dist))
-        .collect();
-    Some(calc_area(&steps))
+/// The directions in the order of their letters, `URDL`.
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+impl Direction {
+    fn delta(self) -> (i64, i64) {
+        [(-1, 0), (0, 1), (1, 0), (0, -1)][self as usize]
+    }
 }
 
-pub fn part_two(input: &str) -> Option<isize> {
-    let steps = parse_input(input)
-        .iter()
-        .map(|(_, _, col)| {
-            let (head, tail) = col.split_at(5);
-            let direction = match tail.chars().last().unwrap() {
-                '0' => Direction::Right,
-                '1' => Direction::Down,
-                '2' => Direction::Left,
-                '3' => Direction::Up,
-                _ => unreachable!(),
-            };
-            let distance = usize::from_str_radix(head, 16).unwrap();
-            (direction, distance)
-        })
-        .collect();
-    Some(calc_area(&steps))
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Step {
+    direction: Direction,
+    distance: i64,
 }
 
-fn parse_input(input: &str) -> Vec<(Direction, usize, String)> {
-    input
-        .trim_end()
-        .lines()
-        .map(|l| {
-            let (raw_dir, rest) = l.split_once(' ').unwrap();
-            let (raw_dist, raw_colour) = rest.split_once(' ').unwrap();
-            let dir = match raw_dir {
-                "U" => Direction::Up,
-                "D" => Direction::Down,
-                "L" => Direction::Left,
-                "R" => Direction::Right,
-                _ => unreachable!(),
-            };
-            let dist = raw_dist.parse().unwrap();
-            let colour = raw_colour
-                .chars()
-                .filter(|&c| c != '(' && c != '#' && c != ')')
-                .collect::<String>();
-            (dir, dist, colour)
-        })
-        .collect()
+/// One line of the dig plan, read both ways: the step as written with its trench colour,
+/// and the step the colour's hex digits encode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Instruction {
+    step: Step,
+    colour: [u8; 3],
+    decoded: Step,
 }
 
-fn calc_area(steps: &Vec<(Direction, usize)>) -> isize {
-    let mut area = 0;
-    let mut col: isize = 0;
-    let mut row: isize = 0;
-    // Shoelace formula
-    for &(direction, distance) in steps {
-        let (old_row, old_col) = (row, col);
-        (row, col, _) = step_pos(row, col, direction, distance as isize);
-        area += (col + old_col) * (row - old_row) + distance as isize;
+pub fn part_one(input: &str) -> Option<u64> {
+    Some(parse::report(DigPlan::new(input))?.volume(false))
+}
+
+pub fn part_two(input: &str) -> Option<u64> {
+    Some(parse::report(DigPlan::new(input))?.volume(true))
+}
+
+/// Draws each part's trench and lagoon to `data/renders/18-1.ppm` and `18-2.ppm`.
+fn debug(input: &str) {
+    let Some(plan) = parse::report(DigPlan::new(input)) else {
+        return;
+    };
+    for (part, decoded) in [(1, false), (2, true)] {
+        let file_name = format!("{DAY}-{part}.ppm");
+        let image = plan.render_ppm(decoded, MAX_IMAGE_SIZE);
+        if let Err(err) = template::write_bytes("renders", &file_name, &image) {
+            eprintln!("could not write {file_name}: {err}");
+        }
     }
-    area / 2 + 1
 }
 
-fn step_pos(
-    row: isize,
-    col: isize,
-    direction: Direction,
-    dist: isize,
-) -> (isize, isize, Direction) {
-    let (delta_vert, delta_horz) = [(0, 0), (-1, 0), (0, 1), (1, 0), (0, -1)][direction as usize];
-    (
-        row as isize + delta_vert * dist,
-        col as isize + delta_horz * dist,
-        direction,
-    )
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DigPlan {
+    instructions: Vec<Instruction>,
+}
+
+impl DigPlan {
+    fn new(input: &str) -> Result<Self, ParseError> {
+        let instructions = parse::each_line(input.trim_end(), |l| {
+            let (raw_dir, rest) = parse::key_value(l, " ")?;
+            let (raw_dist, raw_colour) =
+                parse::key_value(rest, " ").map_err(|err| err.within(l, rest))?;
+            let (direction, _) = parse::one_of(raw_dir, "URDL")?;
+            let step = Step {
+                direction: DIRECTIONS[direction],
+                distance: parse::value(raw_dist).map_err(|err| err.within(l, raw_dist))?,
+            };
+            let hex = raw_colour.trim_matches(['(', '#', ')']);
+            let value = match hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                true => u32::from_str_radix(hex, 16).unwrap(),
+                false => {
+                    let kind = ParseErrorKind::InvalidValue(raw_colour.to_string());
+                    return Err(ParseError::new(1, 1, kind).within(l, raw_colour));
+                }
+            };
+            let colour = [16, 8, 0].map(|shift| (value >> shift) as u8);
+            let decoded = Step {
+                direction: match value & 0xf {
+                    0 => Direction::Right,
+                    1 => Direction::Down,
+                    2 => Direction::Left,
+                    3 => Direction::Up,
+                    _ => {
+                        let kind = ParseErrorKind::ExpectedOneOf("0123".to_string());
+                        return Err(ParseError::new(1, 6, kind).within(l, hex));
+                    }
+                },
+                distance: (value >> 4) as i64,
+            };
+            Ok(Instruction {
+                step,
+                colour,
+                decoded,
+            })
+        })?;
+        Ok(Self { instructions })
+    }
+
+    /// Returns the corners of the trench, starting and ending at the origin.
+    fn corners(&self, decoded: bool) -> Vec<(i64, i64)> {
+        let steps = self.instructions.iter().map(|instruction| {
+            let step = if decoded {
+                instruction.decoded
+            } else {
+                instruction.step
+            };
+            (step.direction.delta(), step.distance)
+        });
+        let mut corners = vec![(0, 0)];
+        corners.extend(polygon::vertices_from_steps(steps));
+        corners
+    }
+
+    /// Counts the cubic metres dug out: the trench along the plan and the lagoon inside it.
+    fn volume(&self, decoded: bool) -> u64 {
+        let corners = self.corners(decoded);
+        polygon::lattice_points(&corners[1..])
+    }
+
+    /// Draws the trench in its colours and the lagoon in grey as a binary PPM image.
+    ///
+    /// Each pixel covers a square of metres small enough that the longest side of the
+    /// image is at most `max_size` pixels, and shows the lagoon when the metre at the
+    /// square's centre is inside it.
+    fn render_ppm(&self, decoded: bool, max_size: i64) -> Vec<u8> {
+        let corners = self.corners(decoded);
+        let (min_row, max_row) = min_max(corners.iter().map(|&(row, _)| row));
+        let (min_col, max_col) = min_max(corners.iter().map(|&(_, col)| col));
+        let span = (max_row - min_row).max(max_col - min_col) + 1;
+        let scale = ((span + max_size - 1) / max_size).max(1);
+        let height = (max_row - min_row) / scale + 1;
+        let width = (max_col - min_col) / scale + 1;
+        let mut pixels = vec![[255; 3]; (height * width) as usize];
+
+        for y in 0..height {
+            let row = min_row + y * scale + scale / 2;
+            let mut crossings = corners
+                .windows(2)
+                .filter(|w| {
+                    w[0].1 == w[1].1 && (w[0].0.min(w[1].0)..w[0].0.max(w[1].0)).contains(&row)
+                })
+                .map(|w| w[0].1)
+                .collect::<Vec<_>>();
+            crossings.sort_unstable();
+            for x in 0..width {
+                let col = min_col + x * scale + scale / 2;
+                if crossings.iter().filter(|&&c| c < col).count() % 2 == 1 {
+                    pixels[(y * width + x) as usize] = [192; 3];
+                }
+            }
+        }
+
+        for (w, instruction) in corners.windows(2).zip(&self.instructions) {
+            let pixel = |(row, col): (i64, i64)| ((row - min_row) / scale, (col - min_col) / scale);
+            let ((y1, x1), (y2, x2)) = (pixel(w[0]), pixel(w[1]));
+            for y in y1.min(y2)..=y1.max(y2) {
+                for x in x1.min(x2)..=x1.max(x2) {
+                    pixels[(y * width + x) as usize] = instruction.colour;
+                }
+            }
+        }
+
+        let mut image = format!("P6\n{width} {height}\n255\n").into_bytes();
+        image.extend(pixels.into_iter().flatten());
+        image
+    }
+}
+
+fn min_max(values: impl Iterator<Item = i64>) -> (i64, i64) {
+    values.fold((i64::MAX, i64::MIN), |(min, max), v| {
+        (min.min(v), max.max(v))
+    })
 }
 
 #[cfg(test)]
@@ -102,4 +204,68 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(952408144115));
     }
+
+    #[test]
+    fn test_render_ppm() {
+        let plan = DigPlan::new(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        assert_eq!(plan.instructions[0].colour, [0x70, 0xc7, 0x10]);
+        assert_eq!(
+            plan.instructions[0].decoded,
+            Step {
+                direction: Direction::Right,
+                distance: 461937
+            }
+        );
+
+        let image = plan.render_ppm(false, MAX_IMAGE_SIZE);
+        let header = b"P6\n7 10\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        let pixels = image[header.len()..].chunks(3).collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 70);
+        assert_eq!(pixels.iter().filter(|&&p| p == [192; 3]).count(), 24);
+        assert_eq!(pixels.iter().filter(|&&p| p != [255; 3]).count(), 62);
+
+        let image = plan.render_ppm(true, 100);
+        let header_len = image
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .nth(2)
+            .unwrap()
+            .0;
+        let header = std::str::from_utf8(&image[..header_len]).unwrap();
+        let size = header.lines().nth(1).unwrap();
+        let (width, height) = size.split_once(' ').unwrap();
+        let (width, height) = (
+            width.parse::<usize>().unwrap(),
+            height.parse::<usize>().unwrap(),
+        );
+        assert!(width <= 100 && height <= 100);
+        assert_eq!(image.len(), header_len + 1 + 3 * width * height);
+    }
+
+    #[test]
+    fn test_malformed_plan() {
+        let error = |input: &str| DigPlan::new(input).unwrap_err().to_string();
+        assert_eq!(
+            error("R 6 (#70c710)\nX 5 (#0dc571)\n"),
+            "line 2, column 1: expected one of `URDL`"
+        );
+        assert_eq!(
+            error("R 6 (#70c710)\nD 5\n"),
+            "line 2, column 4: expected separator ` `"
+        );
+        assert_eq!(
+            error("R x (#70c710)\n"),
+            "line 1, column 3: invalid value `x`"
+        );
+        assert_eq!(
+            error("R 6 (#70c7g0)\n"),
+            "line 1, column 5: invalid value `(#70c7g0)`"
+        );
+        assert_eq!(
+            error("R 6 (#70c714)\n"),
+            "line 1, column 12: expected one of `0123`"
+        );
+    }
 }
@@ -1,64 +1,74 @@
-advent_of_code::solution!(19);
+advent_of_code::solution!(19, debug);
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
-type Category = char;
-type Operation = char;
-type Rating = HashMap<Category, usize>;
+use advent_of_code::parse::{self, ParseError};
+
+/// Index of a category in `xmas` order.
+type Category = usize;
+/// Whether a rating must be less or greater than the value.
+type Operation = Ordering;
+type Rating = [usize; 4];
 type Conditions<'a> = Vec<(Option<(Category, Operation, usize)>, &'a str)>;
+type Workflows<'a> = HashMap<&'a str, Conditions<'a>>;
+/// Inclusive `[lo, hi]` bounds on each category, in `xmas` order.
+type Bounds = [(usize, usize); 4];
 
 pub fn part_one(input: &str) -> Option<usize> {
-    let (workflows, ratings) = parse_input(input);
+    let (workflows, ratings) = parse::report(parse_input(input))?;
     let sum = ratings
         .iter()
         .filter_map(|categories| {
-            is_accepted(&workflows, categories).then_some(categories.values().sum::<usize>())
+            is_accepted(&workflows, categories).then_some(categories.iter().sum::<usize>())
         })
         .sum();
     Some(sum)
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
-    let (workflows, _) = parse_input(input);
-    let ranges = HashMap::from_iter("xmas".chars().map(|c| (c, (1..=4000).collect())));
-    Some(ranges_accepted(&workflows, "in", ranges))
+    let (workflows, _) = parse::report(parse_input(input))?;
+    let accepted = accepted_bounds(&workflows);
+    Some(accepted.iter().map(|(bounds, _)| volume(bounds)).sum())
 }
 
-fn parse_input(input: &str) -> (HashMap<&str, Conditions>, Vec<Rating>) {
-    let (raw_workflows, raw_ratings) = input.trim_end().split_once("\n\n").unwrap();
-    let workflows = HashMap::from_iter(raw_workflows.lines().map(|l| {
-        let (label, rest) = l.split_once("{").unwrap();
-        let conditions = rest
-            .split(",")
-            .map(|item| match item.split_once(':') {
-                Some((rest, label)) => {
-                    let (category, condition) = rest.split_at(1);
-                    let (op, value) = condition.split_at(1);
-                    (
-                        Some((
-                            category.chars().last().unwrap(),
-                            op.chars().last().unwrap(),
-                            value.parse().unwrap(),
-                        )),
-                        label,
-                    )
-                }
-                None => (None, item.trim_end_matches(|c| c == '}')),
-            })
-            .collect();
-        (label, conditions)
-    }));
-    let ratings = raw_ratings
-        .lines()
-        .map(|l| {
-            HashMap::from_iter("xmas".chars().zip(l.splitn(4, ",").map(|item| {
-                item.trim_matches(|c: char| !c.is_digit(10))
-                    .parse()
-                    .unwrap()
-            })))
+/// Lists every accepted region of ratings with the workflows that lead there.
+fn debug(input: &str) {
+    if let Some((workflows, _)) = parse::report(parse_input(input)) {
+        for (bounds, path) in accepted_bounds(&workflows) {
+            println!("{} via {}", describe(&bounds), path.join(" -> "));
+        }
+    }
+}
+
+fn parse_input(input: &str) -> Result<(Workflows<'_>, Vec<Rating>), ParseError> {
+    let (raw_workflows, raw_ratings) = parse::key_value(input.trim_end(), "\n\n")?;
+    let workflows = parse::each_line(raw_workflows, |l| {
+        let (label, rest) = parse::key_value(l, "{")?;
+        let rest = rest.trim_end_matches('}');
+        let conditions = parse::each_item(rest, ",", |item| match item.split_once(':') {
+            Some((rest, label)) => {
+                let (category, condition) = parse::one_of(rest, "xmas")?;
+                let (op, value) =
+                    parse::one_of(condition, "<>").map_err(|err| err.within(item, condition))?;
+                Ok((
+                    Some((
+                        category,
+                        [Ordering::Less, Ordering::Greater][op],
+                        parse::value(value).map_err(|err| err.within(item, value))?,
+                    )),
+                    label,
+                ))
+            }
+            None => Ok((None, item)),
         })
-        .collect();
-    (workflows, ratings)
+        .map_err(|err| err.within(l, rest))?;
+        Ok((label, conditions))
+    })?;
+    let ratings = parse::each_line(raw_ratings, |l| parse::exactly(l, parse::ints(l)?))
+        .map_err(|err| err.within(input, raw_ratings))?;
+
+    Ok((HashMap::from_iter(workflows), ratings))
 }
 
 fn is_accepted(workflows: &HashMap<&str, Conditions>, rating: &Rating) -> bool {
@@ -68,11 +78,9 @@ fn is_accepted(workflows: &HashMap<&str, Conditions>, rating: &Rating) -> bool {
         curr = workflow
             .iter()
             .find_map(|&(condition, dest_workflow)| match condition {
-                Some((category, op, value)) => match op {
-                    '<' if rating[&category] < value => Some(dest_workflow),
-                    '>' if rating[&category] > value => Some(dest_workflow),
-                    _ => None,
-                },
+                Some((category, op, value)) => {
+                    (rating[category].cmp(&value) == op).then_some(dest_workflow)
+                }
                 None => Some(dest_workflow),
             })
             .unwrap();
@@ -84,28 +92,63 @@ fn is_accepted(workflows: &HashMap<&str, Conditions>, rating: &Rating) -> bool {
     }
 }
 
-fn ranges_accepted(
-    workflows: &HashMap<&str, Conditions>,
-    curr: &str,
-    mut ranges: HashMap<Category, Vec<usize>>,
-) -> usize {
+/// Returns every region of ratings that ends up accepted, with the workflows that lead there.
+fn accepted_bounds<'a>(workflows: &Workflows<'a>) -> Vec<(Bounds, Vec<&'a str>)> {
+    let mut accepted = vec![];
+    visit(workflows, "in", [(1, 4000); 4], &mut vec![], &mut accepted);
+    accepted
+}
+
+fn visit<'a>(
+    workflows: &Workflows<'a>,
+    curr: &'a str,
+    mut bounds: Bounds,
+    path: &mut Vec<&'a str>,
+    accepted: &mut Vec<(Bounds, Vec<&'a str>)>,
+) {
+    path.push(curr);
     match curr {
-        "R" => 0,
-        "A" => ranges.iter().map(|(_, v)| v.len()).product(),
-        _ => workflows[curr].iter().fold(0, |acc, (condition, label)| {
-            let mut ranges_to_check = ranges.clone();
-            if let Some((category, op, value)) = *condition {
-                let (successes, fails) = ranges[&category].iter().partition(|&rating| match op {
-                    '<' => rating < &value,
-                    '>' => rating > &value,
-                    _ => unreachable!(),
-                });
-                ranges_to_check.insert(category, successes);
-                ranges.insert(category, fails);
+        "R" => {}
+        "A" => accepted.push((bounds, path.clone())),
+        _ => {
+            for &(condition, label) in &workflows[curr] {
+                let mut matched = bounds;
+                if let Some((i, op, value)) = condition {
+                    let (lo, hi) = bounds[i];
+                    (matched[i], bounds[i]) = match op {
+                        Ordering::Less => {
+                            ((lo, hi.min(value.saturating_sub(1))), (lo.max(value), hi))
+                        }
+                        Ordering::Greater => ((lo.max(value + 1), hi), (lo, hi.min(value))),
+                        Ordering::Equal => unreachable!(),
+                    };
+                }
+                if volume(&matched) > 0 {
+                    visit(workflows, label, matched, path, accepted);
+                }
+                if volume(&bounds) == 0 {
+                    break;
+                }
             }
-            acc + ranges_accepted(&workflows, label, ranges_to_check)
-        }),
+        }
     }
+    path.pop();
+}
+
+fn volume(bounds: &Bounds) -> usize {
+    bounds
+        .iter()
+        .map(|&(lo, hi)| (hi + 1).saturating_sub(lo))
+        .product()
+}
+
+fn describe(bounds: &Bounds) -> String {
+    "xmas"
+        .chars()
+        .zip(bounds)
+        .map(|(category, (lo, hi))| format!("{category}={lo}..={hi}"))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[cfg(test)]
@@ -123,4 +166,40 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(167409079868000));
     }
+
+    #[test]
+    fn test_accepted_bounds() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let (workflows, _) = parse_input(&input).unwrap();
+        let accepted = accepted_bounds(&workflows);
+        assert!(accepted
+            .iter()
+            .all(|(_, path)| path.first() == Some(&"in") && path.last() == Some(&"A")));
+        assert_eq!(
+            describe(&accepted[0].0),
+            "x=1..=1415 m=1..=4000 a=1..=2005 s=1..=1350"
+        );
+        assert_eq!(accepted[0].1, vec!["in", "px", "qkq", "A"]);
+    }
+
+    #[test]
+    fn test_malformed_conditions() {
+        let error = |input: &str| parse_input(input).map(|_| ()).unwrap_err().to_string();
+        assert_eq!(
+            error("in{y<5:A,R}\n\n{x=1,m=2,a=3,s=4}"),
+            "line 1, column 4: expected one of `xmas`"
+        );
+        assert_eq!(
+            error("in{A}\nab{x=5:A,R}\n\n{x=1,m=2,a=3,s=4}"),
+            "line 2, column 5: expected one of `<>`"
+        );
+        assert_eq!(
+            error("in{:A,R}\n\n{x=1,m=2,a=3,s=4}"),
+            "line 1, column 4: expected one of `xmas`"
+        );
+        assert_eq!(
+            error("in{é<1:A,R}\n\n{x=1,m=2,a=3,s=4}"),
+            "line 1, column 4: expected one of `xmas`"
+        );
+    }
 }
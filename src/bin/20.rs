@@ -1,112 +1,348 @@
-use num::integer::lcm;
-use std::collections::{HashMap, HashSet, VecDeque};
+use advent_of_code::math::{self, MathError};
+use advent_of_code::parse::{self, ParseError, ParseErrorKind};
+use itertools::Itertools;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Write};
+use std::rc::Rc;
 
-advent_of_code::solution!(20);
+advent_of_code::solution!(20, debug);
+
+/// The most button presses to simulate while looking for the cycles feeding `rx`.
+const MAX_PRESSES: u64 = 100_000;
+
+/// How many times part one pushes the button.
+const BUTTON_PRESSES: usize = 1000;
+
+/// How many presses to print when run with `--debug`.
+const TRACE_PRESSES: u64 = 4;
 
 pub fn part_one(input: &str) -> Option<u64> {
-    solve(input, false).0
+    let mut network = parse::report(Network::new(input))?;
+    let (low, high) =
+        (0..BUTTON_PRESSES)
+            .flat_map(|_| network.press())
+            .fold((0, 0), |(low, high), pulse| match pulse.high {
+                true => (low, high + 1),
+                false => (low + 1, high),
+            });
+    Some(low * high)
 }
 
 pub fn part_two(input: &str) -> Option<u64> {
-    solve(input, true).1
+    let mut network = parse::report(Network::new(input))?;
+    parse::report(presses_until_rx(&mut network))
 }
 
-fn parse_input(input: &str) -> HashMap<&str, (char, Vec<&str>)> {
-    HashMap::from_iter(input.trim_end().lines().map(|l| {
-        let (module, rest) = l.split_once(" -> ").unwrap();
-        let dest_modules = rest.split(", ").collect();
-        if module == "broadcaster" {
-            (module, ('b', dest_modules))
-        } else {
-            (&module[1..], (module.as_bytes()[0] as char, dest_modules))
+/// Replays part one's presses, writing every pulse to `data/traces/20.txt`.
+fn debug(input: &str) {
+    let Some(mut network) = parse::report(Network::new(input)) else {
+        return;
+    };
+    let trace = record_trace(&mut network);
+    for _ in 0..BUTTON_PRESSES {
+        network.press();
+    }
+    if let Err(err) = advent_of_code::template::write_file("traces", DAY, &trace.take()) {
+        eprintln!("could not write trace: {err}");
+    }
+}
+
+/// Prints the first [`TRACE_PRESSES`] presses and records every pulse into the returned buffer.
+fn record_trace<'a>(network: &mut Network<'a>) -> Rc<RefCell<String>> {
+    let trace = Rc::new(RefCell::new(String::new()));
+    let buffer = trace.clone();
+    network.observe(move |press, pulse| {
+        if press <= TRACE_PRESSES {
+            println!("{press}: {pulse}");
         }
-    }))
+        writeln!(buffer.borrow_mut(), "{press}: {pulse}").unwrap();
+    });
+    trace
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Button,
+    Broadcaster,
+    FlipFlop,
+    Conjunction,
+    /// A module that is only ever sent to, such as `output` or `rx`.
+    Untyped,
 }
 
-pub fn solve(input: &str, find_rx: bool) -> (Option<u64>, Option<u64>) {
-    let modules = parse_input(input);
-    let (mut low, mut high) = (0, 0);
+/// A pulse sent from one module to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pulse<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub high: bool,
+}
+
+impl Display for Pulse<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = if self.high { "high" } else { "low" };
+        write!(f, "{} -{level}-> {}", self.from, self.to)
+    }
+}
+
+type Observer<'a> = Box<dyn FnMut(u64, &Pulse<'a>) + 'a>;
 
-    let mut module_states = HashSet::new();
-    let mut conjunctions = HashMap::<&str, HashMap<&str, bool>>::new();
-    for (module, (_, connections)) in modules.clone() {
-        for connection in connections {
-            let Some(&(module_type, _)) = modules.get(connection) else {
-                continue;
+/// A network of pulse modules, including the button, that can be pressed repeatedly.
+pub struct Network<'a> {
+    names: Vec<&'a str>,
+    kinds: Vec<Kind>,
+    /// Each module's destinations, as the destination and this module's slot in its memory.
+    outputs: Vec<Vec<(usize, usize)>>,
+    inputs: Vec<Vec<usize>>,
+    on: Vec<bool>,
+    /// The last pulse each conjunction received from each of its inputs.
+    memory: Vec<Vec<bool>>,
+    presses: u64,
+    observers: Vec<Observer<'a>>,
+}
+
+impl<'a> Network<'a> {
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        let declared = parse::each_line(input.trim_end(), |l| {
+            let (module, rest) = parse::key_value(l, " -> ")?;
+            let (kind, name) = match parse::one_of(module, "%&") {
+                Ok((0, name)) => (Kind::FlipFlop, name),
+                Ok((_, name)) => (Kind::Conjunction, name),
+                Err(_) if module == "broadcaster" => (Kind::Broadcaster, module),
+                Err(_) => {
+                    return Err(ParseError::new(
+                        1,
+                        1,
+                        ParseErrorKind::InvalidValue(module.to_string()),
+                    ))
+                }
             };
-            if module_type == '&' {
-                conjunctions
-                    .entry(connection)
-                    .or_default()
-                    .insert(module, false);
+            Ok((name, kind, rest.split(", ").collect_vec()))
+        })?;
+
+        let mut names = vec!["button"];
+        let mut kinds = vec![Kind::Button];
+        for &(name, kind, _) in &declared {
+            names.push(name);
+            kinds.push(kind);
+        }
+        let destinations = declared.iter().flat_map(|(_, _, d)| d.iter().copied());
+        for destination in std::iter::once("broadcaster").chain(destinations) {
+            if !names.contains(&destination) {
+                names.push(destination);
+                kinds.push(Kind::Untyped);
             }
         }
-    }
+        let index = |name: &str| names.iter().position(|&n| n == name).unwrap();
 
-    // As "rx" (the goal low-pulse module) is the output of &bq, this will only recieve a low pulse when all the inputs to bq are high.
-    // The inputs to bq are &vg, &kp, &gc, &tx
-
-    let mut bq_ins = [None; 4];
-
-    let mut part_1 = None;
-    for press in 1.. {
-        let mut queue = VecDeque::from_iter([("broadcaster", "button", false)]);
-        while let Some((module, prev, is_high)) = queue.pop_front() {
-            if is_high {
-                high += 1;
-                if module == "bq" {
-                    match prev {
-                        "vg" => bq_ins[0] = bq_ins[0].or(Some(press as u64)),
-                        "kp" => bq_ins[1] = bq_ins[1].or(Some(press as u64)),
-                        "gc" => bq_ins[2] = bq_ins[2].or(Some(press as u64)),
-                        "tx" => bq_ins[3] = bq_ins[3].or(Some(press as u64)),
-                        _ => {}
-                    }
-                }
-            } else {
-                low += 1;
+        let mut outputs = vec![vec![]; names.len()];
+        let mut inputs = vec![vec![]; names.len()];
+        let broadcaster = index("broadcaster");
+        outputs[0].push((broadcaster, 0));
+        inputs[broadcaster].push(0);
+        for (module, (_, _, destinations)) in declared.iter().enumerate() {
+            let module = module + 1;
+            for &destination in destinations {
+                let destination = index(destination);
+                outputs[module].push((destination, inputs[destination].len()));
+                inputs[destination].push(module);
             }
-            let Some((module_type, connections)) = modules.get(module) else {
-                continue;
+        }
+        let memory = inputs.iter().map(|i| vec![false; i.len()]).collect();
+        Ok(Self {
+            on: vec![false; names.len()],
+            names,
+            kinds,
+            outputs,
+            inputs,
+            memory,
+            presses: 0,
+            observers: vec![],
+        })
+    }
+
+    /// Registers `observer` to be called with the press number and every pulse sent.
+    pub fn observe(&mut self, observer: impl FnMut(u64, &Pulse<'a>) + 'a) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Presses the button once, returning every pulse sent in the order it was handled.
+    pub fn press(&mut self) -> Vec<Pulse<'a>> {
+        self.presses += 1;
+        let mut pulses = vec![];
+        let mut queue = VecDeque::from([(0, self.outputs[0][0], false)]);
+        while let Some((from, (to, slot), high)) = queue.pop_front() {
+            let pulse = Pulse {
+                from: self.names[from],
+                to: self.names[to],
+                high,
             };
-            let pulse = match module_type {
-                'b' => false,
-                '%' => {
-                    if is_high {
-                        continue;
-                    }
-                    let on = module_states.contains(module);
-                    if on {
-                        module_states.remove(module);
-                    } else {
-                        module_states.insert(module);
-                    }
-                    !on
+            for observer in &mut self.observers {
+                observer(self.presses, &pulse);
+            }
+            pulses.push(pulse);
+            let sent = match self.kinds[to] {
+                Kind::Broadcaster => high,
+                Kind::FlipFlop if high => continue,
+                Kind::FlipFlop => {
+                    self.on[to] = !self.on[to];
+                    self.on[to]
                 }
-                '&' => {
-                    conjunctions.get_mut(module).unwrap().insert(prev, is_high);
-                    !conjunctions[module].values().all(|&b| b)
+                Kind::Conjunction => {
+                    self.memory[to][slot] = high;
+                    !self.memory[to].iter().all(|&h| h)
                 }
-                _ => unreachable!(),
+                Kind::Button | Kind::Untyped => continue,
             };
-            queue.extend(
-                connections
+            queue.extend(self.outputs[to].iter().map(|&output| (to, output, sent)));
+        }
+        pulses
+    }
+
+    /// Returns the number of times the button has been pressed.
+    pub fn presses(&self) -> u64 {
+        self.presses
+    }
+
+    /// Returns the flip-flop states and conjunction memories, which decide every future pulse.
+    fn state(&self) -> (Vec<bool>, Vec<Vec<bool>>) {
+        (self.on.clone(), self.memory.clone())
+    }
+
+    /// Presses until the state repeats, returning when it was first seen and the cycle length.
+    pub fn find_cycle(&mut self, max_presses: u64) -> Option<(u64, u64)> {
+        let mut seen = HashMap::from([(self.state(), self.presses)]);
+        for _ in 0..max_presses {
+            self.press();
+            let state = self.state();
+            if let Some(&first) = seen.get(&state) {
+                return Some((first, self.presses - first));
+            }
+            seen.insert(state, self.presses);
+        }
+        None
+    }
+
+    /// Finds the single conjunction that sends to `rx`, returning it and its inputs.
+    fn rx_feeder(&self) -> Result<(usize, Vec<usize>), RxError> {
+        let rx = self
+            .names
+            .iter()
+            .position(|&n| n == "rx")
+            .ok_or(RxError::NoFeeder)?;
+        let feeder = match self.inputs[rx][..] {
+            [] => return Err(RxError::NoFeeder),
+            [feeder] => feeder,
+            ref feeders => {
+                let mut names = feeders
                     .iter()
-                    .map(|&connection| (connection, module, pulse)),
-            );
+                    .map(|&m| self.names[m].to_string())
+                    .collect_vec();
+                names.sort();
+                return Err(RxError::MultipleFeeders(names));
+            }
+        };
+        if self.kinds[feeder] != Kind::Conjunction {
+            return Err(RxError::NotConjunction(self.names[feeder].to_string()));
+        }
+        Ok((feeder, self.inputs[feeder].clone()))
+    }
+}
+
+/// Finds the first press on which every input of the conjunction feeding `rx` sends high.
+fn presses_until_rx(network: &mut Network) -> Result<u64, RxError> {
+    let (feeder, inputs) = network.rx_feeder()?;
+    let feeder = network.names[feeder];
+    let inputs = inputs.iter().map(|&m| network.names[m]).collect_vec();
+    // The presses on which each input of the feeder sent it a high pulse, up to three.
+    let mut high_presses = vec![vec![]; inputs.len()];
+
+    while network.presses() < MAX_PRESSES {
+        for pulse in network.press() {
+            if pulse.high && pulse.to == feeder {
+                let i = inputs.iter().position(|&m| m == pulse.from).unwrap();
+                let press = network.presses();
+                if high_presses[i].len() < 3 && high_presses[i].last() != Some(&press) {
+                    high_presses[i].push(press);
+                }
+            }
         }
-        if press == 1000 {
-            part_1 = Some(low * high);
-            if !find_rx {
-                return (part_1, None);
+        if high_presses.iter().all(|p| p.len() == 3) {
+            let mut irregular = inputs
+                .iter()
+                .zip(&high_presses)
+                .filter(|(_, p)| p[2] - p[1] != p[1] - p[0])
+                .map(|(&m, _)| m.to_string())
+                .collect_vec();
+            if !irregular.is_empty() {
+                irregular.sort();
+                return Err(RxError::UnsupportedShape(irregular));
             }
+            let sequences = high_presses
+                .iter()
+                .map(|high| (high[0], high[1] - high[0]))
+                .collect_vec();
+            return math::align(&sequences).map_err(RxError::Unaligned);
         }
-        if bq_ins.iter().all(|o| o.is_some()) {
-            let part_2 = bq_ins.iter().fold(1, |acc, press| lcm(acc, press.unwrap()));
-            return (part_1, Some(part_2));
+    }
+    let mut missing = inputs
+        .iter()
+        .zip(&high_presses)
+        .filter(|(_, p)| p.len() < 3)
+        .map(|(&m, _)| m.to_string())
+        .collect_vec();
+    missing.sort();
+    Err(RxError::NoCycle(missing))
+}
+
+/// Why the presses needed to send a low pulse to `rx` could not be found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RxError {
+    /// No module sends to `rx`.
+    NoFeeder,
+    /// More than one module sends to `rx`.
+    MultipleFeeders(Vec<String>),
+    /// The module that sends to `rx` is not a conjunction.
+    NotConjunction(String),
+    /// These inputs of the feeder did not send three high pulses within [`MAX_PRESSES`].
+    NoCycle(Vec<String>),
+    /// These inputs of the feeder did not send high at a constant period.
+    UnsupportedShape(Vec<String>),
+    /// The inputs' cycles never all send high on the same press.
+    Unaligned(MathError),
+}
+
+impl Display for RxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RxError::NoFeeder => write!(f, "no module sends pulses to rx"),
+            RxError::MultipleFeeders(feeders) => write!(
+                f,
+                "expected a single module sending to rx, found {}",
+                feeders.join(", ")
+            ),
+            RxError::NotConjunction(feeder) => {
+                write!(
+                    f,
+                    "the module sending to rx, {feeder}, is not a conjunction"
+                )
+            }
+            RxError::NoCycle(inputs) => write!(
+                f,
+                "no cycle found within {MAX_PRESSES} presses for {}",
+                inputs.join(", ")
+            ),
+            RxError::UnsupportedShape(inputs) => write!(
+                f,
+                "high pulses from {} to the module sending to rx are not periodic",
+                inputs.join(", ")
+            ),
+            RxError::Unaligned(err) => {
+                write!(f, "the inputs feeding rx never send high together: {err}")
+            }
         }
     }
-    unreachable!();
 }
 
 #[cfg(test)]
@@ -117,7 +353,100 @@ mod tests {
     fn test_part_one() {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(32000000));
+        let result =
+            part_one("broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output\n");
+        assert_eq!(result, Some(11687500));
     }
 
-    // Part 2 doesn't work as no example for part 2 was given.
+    #[test]
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file_part(
+            "examples", DAY, 2,
+        ));
+        assert_eq!(result, Some(2));
+    }
+
+    #[test]
+    fn test_press_order() {
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let mut network = Network::new(&example).unwrap();
+        let trace = network.press().iter().map(Pulse::to_string).collect_vec();
+        assert_eq!(
+            trace,
+            [
+                "button -low-> broadcaster",
+                "broadcaster -low-> a",
+                "broadcaster -low-> b",
+                "broadcaster -low-> c",
+                "a -high-> b",
+                "b -high-> c",
+                "c -high-> inv",
+                "inv -low-> a",
+                "a -low-> b",
+                "b -low-> c",
+                "c -low-> inv",
+                "inv -high-> a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_observe_and_find_cycle() {
+        let input = "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output\n";
+        let mut network = Network::new(input).unwrap();
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = seen.clone();
+        network.observe(move |press, pulse| log.borrow_mut().push((press, pulse.high)));
+        let pulses = network.press();
+        assert_eq!(seen.borrow().len(), pulses.len());
+        assert!(seen.borrow().iter().all(|&(press, _)| press == 1));
+        assert_eq!(network.find_cycle(10), Some((1, 4)));
+
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let mut network = Network::new(&example).unwrap();
+        assert_eq!(network.find_cycle(10), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_malformed_modules() {
+        assert_eq!(
+            Network::new("broadcaster -> a\n%a > b\n")
+                .err()
+                .map(|e| e.to_string()),
+            Some("line 2, column 7: expected separator ` -> `".to_string())
+        );
+        assert_eq!(
+            Network::new("broadcaster -> a\n!a -> b\n")
+                .err()
+                .map(|e| e.to_string()),
+            Some("line 2, column 1: invalid value `!a`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rx_structure_errors() {
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let mut network = Network::new(&example).unwrap();
+        assert_eq!(presses_until_rx(&mut network), Err(RxError::NoFeeder));
+
+        let mut offset =
+            Network::new("broadcaster -> a, p\n%a -> feed\n&p -> feed\n&feed -> rx\n").unwrap();
+        assert_eq!(presses_until_rx(&mut offset), Ok(1));
+
+        let mut unaligned =
+            Network::new("broadcaster -> a, b\n%a -> feed\n%b -> c\n%c -> feed\n&feed -> rx\n")
+                .unwrap();
+        assert_eq!(
+            presses_until_rx(&mut unaligned),
+            Err(RxError::Unaligned(MathError::NoSolution))
+        );
+
+        let mut irregular =
+            Network::new("broadcaster -> a\n%a -> b, x\n%b -> x\n&x -> feed\n&feed -> rx\n")
+                .unwrap();
+        assert_eq!(
+            presses_until_rx(&mut irregular),
+            Err(RxError::UnsupportedShape(vec!["x".to_string()]))
+        );
+    }
 }
@@ -1,7 +1,10 @@
 advent_of_code::solution!(21);
 
+use advent_of_code::math::{self, MathError};
+use advent_of_code::parse;
 use itertools::Itertools;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum Direction {
@@ -18,7 +21,7 @@ pub fn part_one(input: &str) -> Option<usize> {
 
 pub fn part_two(input: &str) -> Option<usize> {
     let (grid, start) = parse_input(input);
-    find_polynomial(&grid, start, 26501365)
+    parse::report(infinite_reachable(&grid, start, 26501365))
 }
 
 fn parse_input(input: &str) -> (Vec<Vec<char>>, (isize, isize)) {
@@ -35,7 +38,11 @@ fn parse_input(input: &str) -> (Vec<Vec<char>>, (isize, isize)) {
     (grid, start)
 }
 
-fn bfs(grid: &Vec<Vec<char>>, start: (isize, isize), steps: usize) -> Option<usize> {
+fn bfs(grid: &[Vec<char>], start: (isize, isize), steps: usize) -> Option<usize> {
+    Some(reachable(grid, start, steps))
+}
+
+fn reachable(grid: &[Vec<char>], start: (isize, isize), steps: usize) -> usize {
     let mut positions = HashSet::from_iter([start]);
     let mut next_positions = HashSet::new();
     for _ in 0..steps {
@@ -48,14 +55,17 @@ fn bfs(grid: &Vec<Vec<char>>, start: (isize, isize), steps: usize) -> Option<usi
                 Direction::Right,
             ] {
                 let (new_row, new_col) = step(r, c, direction);
-                if grid[new_row as usize % grid.len()][new_col as usize % grid[0].len()] != '#' {
+                if grid[new_row.rem_euclid(grid.len() as isize) as usize]
+                    [new_col.rem_euclid(grid[0].len() as isize) as usize]
+                    != '#'
+                {
                     next_positions.insert((new_row, new_col));
                 }
             }
         }
         (positions, next_positions) = (next_positions, positions);
     }
-    Some(positions.len())
+    positions.len()
 }
 
 fn step(row: isize, col: isize, direction: Direction) -> (isize, isize) {
@@ -63,18 +73,324 @@ fn step(row: isize, col: isize, direction: Direction) -> (isize, isize) {
     ((row + delta_vert), (col + delta_horz))
 }
 
-// See https://en.wikipedia.org/wiki/Newton_polynomial
-fn find_polynomial(grid: &Vec<Vec<char>>, start: (isize, isize), steps: usize) -> Option<usize> {
-    let point_1 = bfs(grid, start, steps % grid.len()).unwrap();
-    let point_2 = bfs(grid, start, steps % grid.len() + grid.len()).unwrap();
-    let point_3 = bfs(grid, start, steps % grid.len() + grid.len() * 2).unwrap();
-    let max_traversals = (steps / grid.len()) as isize;
-    let [a, b, c] = [
-        point_1 as isize,
-        (point_2 - point_1) as isize,
-        (point_3 - point_2) as isize,
-    ];
-    Some((a + b * max_traversals + (max_traversals * (max_traversals - 1) / 2) * (c - b)) as usize)
+/// Why [`infinite_reachable`] could not count the plots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReachError {
+    /// No block of tiles small enough to search had settled.
+    Unsettled { radius: usize },
+    /// The count could not be extrapolated from the samples.
+    Extrapolation(MathError),
+}
+
+impl Display for ReachError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReachError::Unsettled { radius } => write!(
+                f,
+                "neither the distances between rings of tiles nor the counts of plots had \
+                 settled by radius {radius}"
+            ),
+            ReachError::Extrapolation(err) => write!(f, "could not extrapolate: {err}"),
+        }
+    }
+}
+
+/// Counts the plots reachable in exactly `steps` steps on the infinitely tiled map.
+fn infinite_reachable(
+    grid: &[Vec<char>],
+    start: (isize, isize),
+    steps: usize,
+) -> Result<usize, ReachError> {
+    if has_clear_centre(grid, start) {
+        find_polynomial(grid, start, steps)
+    } else {
+        tiled_reachable(grid, start, steps)
+    }
+}
+
+/// Counts the plots from distances over growing blocks of tiles, for any map shape.
+fn tiled_reachable(
+    grid: &[Vec<char>],
+    start: (isize, isize),
+    steps: usize,
+) -> Result<usize, ReachError> {
+    let side = grid.len().min(grid[0].len());
+    let covering = steps.div_ceil(side).saturating_sub(1);
+    let largest = (MAX_BLOCK_CELLS / (grid.len() * grid[0].len()))
+        .isqrt()
+        .saturating_sub(3)
+        / 2;
+    let mut radius = covering.min(3).min(largest);
+    loop {
+        let tiles = TileDistances::new(grid, start, radius);
+        if radius == covering {
+            return Ok(tiles.count_within(steps));
+        }
+        if let Some(periods) = tiles.periods() {
+            return Ok(tiles.count_reachable(periods, steps));
+        }
+        if radius >= largest {
+            return tiles.extrapolate(steps);
+        }
+        radius = (2 * radius).min(covering).min(largest);
+    }
+}
+
+/// The most cells, across all its tiles, that [`tiled_reachable`] will search in one block.
+const MAX_BLOCK_CELLS: usize = 1 << 23;
+
+/// Whether the map is square with `S` in its centre and a clear border, row and column.
+fn has_clear_centre(grid: &[Vec<char>], start: (isize, isize)) -> bool {
+    let size = grid.len();
+    let (row, col) = (start.0 as usize, start.1 as usize);
+    let clear = |r: usize, c: usize| grid[r][c] != '#';
+    grid.iter().all(|l| l.len() == size)
+        && size % 2 == 1
+        && (row, col) == (size / 2, size / 2)
+        && (0..size).all(|i| {
+            clear(row, i)
+                && clear(i, col)
+                && clear(0, i)
+                && clear(size - 1, i)
+                && clear(i, 0)
+                && clear(i, size - 1)
+        })
+}
+
+/// Shortest distances from the start to every cell of the tiles up to `radius` tiles out.
+struct TileDistances {
+    radius: usize,
+    height: usize,
+    width: usize,
+    dists: Vec<usize>,
+}
+
+impl TileDistances {
+    fn new(grid: &[Vec<char>], start: (isize, isize), radius: usize) -> Self {
+        let (height, width) = (grid.len(), grid[0].len());
+        let span = 2 * radius + 3;
+        let (block_height, block_width) = (span * height, span * width);
+        let mut dists = vec![usize::MAX; block_height * block_width];
+        let origin = (
+            (radius + 1) * height + start.0 as usize,
+            (radius + 1) * width + start.1 as usize,
+        );
+        let mut queue = VecDeque::from([origin]);
+        dists[origin.0 * block_width + origin.1] = 0;
+        while let Some((r, c)) = queue.pop_front() {
+            let dist = dists[r * block_width + c];
+            for direction in [
+                Direction::Up,
+                Direction::Down,
+                Direction::Left,
+                Direction::Right,
+            ] {
+                let (new_row, new_col) = step(r as isize, c as isize, direction);
+                if !(0..block_height as isize).contains(&new_row)
+                    || !(0..block_width as isize).contains(&new_col)
+                {
+                    continue;
+                }
+                let (new_row, new_col) = (new_row as usize, new_col as usize);
+                let next = &mut dists[new_row * block_width + new_col];
+                if *next == usize::MAX && grid[new_row % height][new_col % width] != '#' {
+                    *next = dist + 1;
+                    queue.push_back((new_row, new_col));
+                }
+            }
+        }
+        Self {
+            radius,
+            height,
+            width,
+            dists,
+        }
+    }
+
+    /// Distance to cell `(r, c)` of the tile `(tile_row, tile_col)` from the centre tile.
+    fn get(&self, tile_row: isize, tile_col: isize, r: usize, c: usize) -> usize {
+        let offset = self.radius as isize + 1;
+        let row = (tile_row + offset) as usize * self.height + r;
+        let col = (tile_col + offset) as usize * self.width + c;
+        self.dists[row * (2 * self.radius + 3) * self.width + col]
+    }
+
+    /// Counts the plots reachable in exactly `steps` steps, if no shorter path leaves the block.
+    fn count_within(&self, steps: usize) -> usize {
+        self.dists
+            .iter()
+            .filter(|&&dist| dist <= steps && (steps - dist).is_multiple_of(2))
+            .count()
+    }
+
+    /// Returns the plots reachable in exactly `s` steps for every `s` the block covers.
+    fn counts_within(&self) -> Vec<usize> {
+        let limit = (self.radius + 1) * self.height.min(self.width);
+        let mut counts = vec![0; limit + 1];
+        for &dist in self.dists.iter().filter(|&&dist| dist <= limit) {
+            counts[dist] += 1;
+        }
+        for s in 2..=limit {
+            counts[s] += counts[s - 2];
+        }
+        counts
+    }
+
+    /// Extrapolates the count from the shortest period over which the counts are quadratic.
+    fn extrapolate(&self, steps: usize) -> Result<usize, ReachError> {
+        let counts = self.counts_within();
+        let last = counts.len() - 1;
+        if steps <= last {
+            return Ok(counts[steps]);
+        }
+        let f = |s: usize| counts[s] as i64;
+        let period = (1..=last / 6)
+            .find(|&p| {
+                let from = (last / 2).max(3 * p);
+                last >= from + 3 * p
+                    && (from..=last)
+                        .all(|s| f(s) - 3 * f(s - p) + 3 * f(s - 2 * p) - f(s - 3 * p) == 0)
+            })
+            .ok_or(ReachError::Unsettled {
+                radius: self.radius,
+            })?;
+        let latest = last - (last - steps % period) % period;
+        let samples = [latest - 2 * period, latest - period, latest].map(|s| counts[s]);
+        extrapolate_quadratic(samples, 2 + (steps - latest) / period)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        (0..self.height).cartesian_product(0..self.width)
+    }
+
+    fn tiles(&self) -> impl Iterator<Item = (isize, isize)> {
+        let radius = self.radius as isize;
+        (-radius..=radius).cartesian_product(-radius..=radius)
+    }
+
+    /// Returns the constant steps per tile on the `[up, down, left, right]` sides, if any.
+    fn periods(&self) -> Option<[usize; 4]> {
+        let radius = self.radius as isize;
+        let sides = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        let mut periods = [self.height, self.height, self.width, self.width];
+        for (period, (dr, dc)) in periods.iter_mut().zip(sides) {
+            let deltas = [radius, radius - 1]
+                .into_iter()
+                .flat_map(|ring| {
+                    self.tiles().filter(move |&(tr, tc)| match dr {
+                        0 => tc == dc * ring && tr.abs() <= ring,
+                        _ => tr == dr * ring && tc.abs() <= ring,
+                    })
+                })
+                .cartesian_product(self.cells().collect_vec())
+                .map(|((tr, tc), (r, c))| {
+                    match (self.get(tr, tc, r, c), self.get(tr - dr, tc - dc, r, c)) {
+                        (_, usize::MAX) => Some(None),
+                        (outer, inner) if outer != usize::MAX && inner != usize::MAX => {
+                            outer.checked_sub(inner).map(Some)
+                        }
+                        _ => None,
+                    }
+                })
+                .collect::<Option<Vec<_>>>()?;
+            match deltas.into_iter().flatten().all_equal_value() {
+                Ok(delta) if delta > 0 => *period = delta,
+                Err(None) => {}
+                _ => return None,
+            }
+        }
+        Some(periods)
+    }
+
+    fn count_reachable(&self, [up, down, left, right]: [usize; 4], steps: usize) -> usize {
+        let radius = self.radius as isize;
+        let period = |tile: isize, before: usize, after: usize| match tile {
+            t if t == -radius => Some(before),
+            t if t == radius => Some(after),
+            _ => None,
+        };
+        self.tiles()
+            .cartesian_product(self.cells().collect_vec())
+            .map(|((tr, tc), (r, c))| {
+                let dist = self.get(tr, tc, r, c);
+                if dist == usize::MAX {
+                    return 0;
+                }
+                match (period(tr, up, down), period(tc, left, right)) {
+                    (None, None) => usize::from(dist <= steps && (steps - dist).is_multiple_of(2)),
+                    (Some(p), None) | (None, Some(p)) => count_line(dist, p, steps),
+                    (Some(p), Some(q)) => count_corner(dist, p, q, steps),
+                }
+            })
+            .sum()
+    }
+}
+
+/// Counts the `n >= 0` for which `dist + n * period` steps is reachable in exactly `steps`.
+fn count_line(dist: usize, period: usize, steps: usize) -> usize {
+    if dist > steps {
+        return 0;
+    }
+    let max_n = (steps - dist) / period;
+    if period.is_multiple_of(2) {
+        return if (steps - dist).is_multiple_of(2) {
+            max_n + 1
+        } else {
+            0
+        };
+    }
+    let first = (steps - dist) % 2;
+    if max_n < first {
+        0
+    } else {
+        (max_n - first) / 2 + 1
+    }
+}
+
+/// Counts the `i, j >= 0` for which `dist + i * period_1 + j * period_2` steps is reachable.
+fn count_corner(dist: usize, period_1: usize, period_2: usize, steps: usize) -> usize {
+    if period_1 != period_2 {
+        return (0..)
+            .map(|i| dist + i * period_1)
+            .take_while(|&d| d <= steps)
+            .map(|d| count_line(d, period_2, steps))
+            .sum();
+    }
+    if dist > steps {
+        return 0;
+    }
+    // With equal periods there are `n + 1` tiles at `dist + n * period`.
+    let max_n = (steps - dist) / period_1;
+    if period_1.is_multiple_of(2) {
+        return if (steps - dist).is_multiple_of(2) {
+            (max_n + 1) * (max_n + 2) / 2
+        } else {
+            0
+        };
+    }
+    let first = (steps - dist) % 2;
+    if max_n < first {
+        return 0;
+    }
+    let k = (max_n - first) / 2;
+    (k + 1) * (first + 1) + k * (k + 1)
+}
+
+/// Counts are quadratic in whole map widths, so three samples a map width apart fix them.
+fn find_polynomial(
+    grid: &[Vec<char>],
+    start: (isize, isize),
+    steps: usize,
+) -> Result<usize, ReachError> {
+    let samples = [0, 1, 2].map(|i| reachable(grid, start, steps % grid.len() + grid.len() * i));
+    extrapolate_quadratic(samples, steps / grid.len())
+}
+
+/// Evaluates at `n` the quadratic through `samples` taken at `0`, `1` and `2`.
+fn extrapolate_quadratic(samples: [usize; 3], n: usize) -> Result<usize, ReachError> {
+    let samples = samples.map(|sample| sample as i64);
+    let count = math::extrapolate(&samples, n as i64).map_err(ReachError::Extrapolation)?;
+    usize::try_from(count).map_err(|_| ReachError::Extrapolation(MathError::Overflow))
 }
 
 #[cfg(test)]
@@ -92,14 +408,38 @@ mod tests {
         assert_eq!(bfs(&grid, start, 6), Some(16));
     }
 
-    // #[test]
-    // fn test_part_two() {
-    //     let (grid, start) = parse_input(&advent_of_code::template::read_file("examples", DAY));
-    //     assert_eq!(find_polynomial(&grid, start, 6), Some(16));
-    //     assert_eq!(find_polynomial(&grid, start, 7), Some(22));
-    //     assert_eq!(find_polynomial(&grid, start, 8), Some(30));
-    //     assert_eq!(find_polynomial(&grid, start, 9), Some(41));
-    //     assert_eq!(find_polynomial(&grid, start, 10), Some(50));
-    //     assert_eq!(find_polynomial(&grid, start, 50), Some(1594));
-    // }
+    #[test]
+    fn test_part_two() {
+        let (grid, start) = parse_input(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(infinite_reachable(&grid, start, 6), Ok(16));
+        assert_eq!(infinite_reachable(&grid, start, 10), Ok(50));
+        assert_eq!(infinite_reachable(&grid, start, 50), Ok(1594));
+        assert_eq!(infinite_reachable(&grid, start, 100), Ok(6536));
+        assert_eq!(infinite_reachable(&grid, start, 500), Ok(167004));
+        assert_eq!(infinite_reachable(&grid, start, 1000), Ok(668697));
+        assert_eq!(infinite_reachable(&grid, start, 5000), Ok(16733044));
+    }
+
+    #[test]
+    fn test_polynomial_matches_tiles() {
+        let input = "...........\n\
+                     .##.....#..\n\
+                     .#.#...###.\n\
+                     ...#.....#.\n\
+                     .##....##..\n\
+                     .....S.....\n\
+                     ..#.#...#..\n\
+                     .#.....##..\n\
+                     ..##.....#.\n\
+                     .#..#..#.#.\n\
+                     ...........\n";
+        let (grid, start) = parse_input(input);
+        assert!(has_clear_centre(&grid, start));
+        for steps in [60, 61, 137, 500, 1001] {
+            assert_eq!(
+                infinite_reachable(&grid, start, steps),
+                tiled_reachable(&grid, start, steps)
+            );
+        }
+    }
 }
@@ -1,89 +1,143 @@
-use std::collections::{HashMap, HashSet};
-
+use advent_of_code::parse::{self, ParseError};
 use itertools::Itertools;
 
 advent_of_code::solution!(22);
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut bricks = parse_input(input);
-    let adjacent = organise_bricks(&mut bricks);
-
-    let safe_bricks = (0..bricks.len()).filter(|&brick| {
-        let mut falling = HashSet::new();
-        disintegrate_all(&adjacent, &mut falling, brick);
-        falling.len() == 1
-    });
+type Brick = (usize, usize, usize, usize, usize, usize);
 
-    Some(safe_bricks.count())
+pub fn part_one(input: &str) -> Option<usize> {
+    let mut bricks = parse::report(parse_input(input))?;
+    let graph = SupportGraph::settle(&mut bricks);
+    Some(
+        (0..graph.len())
+            .filter(|&brick| graph.is_safe(brick))
+            .count(),
+    )
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
-    let mut bricks = parse_input(input);
-    let adjacent = organise_bricks(&mut bricks);
-
-    let would_fall = (0..bricks.len()).map(|brick| {
-        let mut falling = HashSet::new();
-        disintegrate_all(&adjacent, &mut falling, brick);
-        falling.len() - 1
-    });
-    Some(would_fall.sum())
+    let mut bricks = parse::report(parse_input(input))?;
+    let graph = SupportGraph::settle(&mut bricks);
+    Some(graph.chain_reactions().iter().sum())
 }
 
-fn parse_input(input: &str) -> Vec<(usize, usize, usize, usize, usize, usize)> {
-    let mut lines = input
-        .trim_end()
-        .lines()
-        .map(|l| {
-            l.split(|c: char| !c.is_ascii_digit())
-                .map(|w| w.parse::<usize>().unwrap())
-                .collect_tuple()
-                .unwrap()
-        })
-        .collect::<Vec<_>>();
+fn parse_input(input: &str) -> Result<Vec<Brick>, ParseError> {
+    let mut lines = parse::each_line(input.trim_end(), |l| {
+        let [x1, y1, z1, x2, y2, z2] = parse::exactly(l, parse::ints(l)?)?;
+        Ok((x1, y1, z1, x2, y2, z2))
+    })?;
     lines.sort_by_key(|&(_, _, z1, _, _, _)| z1);
-    lines
+    Ok(lines)
 }
 
-fn organise_bricks(
-    bricks: &mut Vec<(usize, usize, usize, usize, usize, usize)>,
-) -> Vec<(HashSet<usize>, HashSet<usize>)> {
-    let mut adjacent = vec![(HashSet::new(), HashSet::new()); bricks.len()];
-    let mut space = HashMap::new();
-    for brick in 0..bricks.len() {
-        let (x1, y1, mut z1, x2, y2, mut z2) = bricks[brick];
-        while z1 > 1
-            && (x1..=x2)
-                .cartesian_product(y1..=y2)
-                .all(|(x, y)| !space.contains_key(&(x, y, z1 - 1)))
-        {
-            z2 -= 1;
-            z1 -= 1;
-        }
-        for (x, y) in (x1..=x2).cartesian_product(y1..=y2) {
-            for z in z1..=z2 {
-                space.insert((x, y, z), brick);
+/// Which settled bricks rest directly on which, with bricks numbered from the bottom up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupportGraph {
+    supports: Vec<Vec<usize>>,
+    supported_by: Vec<Vec<usize>>,
+}
+
+impl SupportGraph {
+    /// Drops `bricks`, sorted by their lowest `z`, as far as they fall and records what
+    /// each comes to rest on.
+    ///
+    /// A map of the top height and topmost brick over each `(x, y)` column lets each brick
+    /// find its resting height and supporters from its own footprint alone.
+    pub fn settle(bricks: &mut [Brick]) -> Self {
+        let width = bricks.iter().map(|b| b.3 + 1).max().unwrap_or(0);
+        let depth = bricks.iter().map(|b| b.4 + 1).max().unwrap_or(0);
+        let mut tops = vec![(0, None); width * depth];
+        let mut graph = Self {
+            supports: vec![vec![]; bricks.len()],
+            supported_by: vec![vec![]; bricks.len()],
+        };
+        for (brick, (x1, y1, z1, x2, y2, z2)) in bricks.iter_mut().enumerate() {
+            let cells = (*x1..=*x2)
+                .cartesian_product(*y1..=*y2)
+                .map(|(x, y)| y * width + x)
+                .collect_vec();
+            let rest = cells.iter().map(|&c| tops[c].0).max().unwrap_or(0);
+            let below = cells
+                .iter()
+                .filter(|&&c| tops[c].0 == rest)
+                .filter_map(|&c| tops[c].1)
+                .sorted()
+                .dedup()
+                .collect_vec();
+            (*z1, *z2) = (rest + 1, rest + 1 + (*z2 - *z1));
+            for c in cells {
+                tops[c] = (*z2, Some(brick));
             }
-            if let Some(&spot) = space.get(&(x, y, z1 - 1)) {
-                adjacent[spot].0.insert(brick);
-                adjacent[brick].1.insert(spot);
+            for &support in &below {
+                graph.supports[support].push(brick);
             }
+            graph.supported_by[brick] = below;
+        }
+        graph
+    }
+
+    pub fn len(&self) -> usize {
+        self.supports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.supports.is_empty()
+    }
+
+    /// Returns the bricks resting directly on `brick`.
+    pub fn supports(&self, brick: usize) -> &[usize] {
+        &self.supports[brick]
+    }
+
+    /// Returns the bricks `brick` rests directly on, which is empty for bricks on the ground.
+    pub fn supported_by(&self, brick: usize) -> &[usize] {
+        &self.supported_by[brick]
+    }
+
+    /// Returns `true` if disintegrating `brick` would not make any other brick fall.
+    pub fn is_safe(&self, brick: usize) -> bool {
+        self.supports[brick]
+            .iter()
+            .all(|&above| self.supported_by[above].len() > 1)
+    }
+
+    /// Returns, for each brick, how many other bricks fall if it is disintegrated.
+    ///
+    /// Removing `a` drops `b` exactly when `a` dominates `b`: every chain of supports from
+    /// the ground up to `b` passes through `a`. Bricks are numbered bottom-up, which is a
+    /// topological order, so each brick's immediate dominator is the lowest common
+    /// ancestor of its supporters in the dominator tree built so far, and the answer is
+    /// the size of each brick's subtree.
+    pub fn chain_reactions(&self) -> Vec<usize> {
+        let ground = self.len();
+        let mut idom = vec![ground; ground + 1];
+        let mut depth = vec![0; ground + 1];
+        for brick in 0..ground {
+            let parent = self.supported_by[brick]
+                .iter()
+                .copied()
+                .reduce(|a, b| common_dominator(&idom, &depth, a, b))
+                .unwrap_or(ground);
+            idom[brick] = parent;
+            depth[brick] = depth[parent] + 1;
+        }
+        let mut sizes = vec![1; ground + 1];
+        for brick in (0..ground).rev() {
+            sizes[idom[brick]] += sizes[brick];
         }
-        bricks[brick] = (x1, y1, z1, x2, y2, z2);
+        sizes[..ground].iter().map(|size| size - 1).collect()
     }
-    return adjacent;
 }
 
-fn disintegrate_all(
-    adjacent: &[(HashSet<usize>, HashSet<usize>)],
-    falling: &mut HashSet<usize>,
-    i: usize,
-) {
-    falling.insert(i);
-    for &above in &adjacent[i].0 {
-        if adjacent[above].1.iter().all(|x| falling.contains(x)) {
-            disintegrate_all(adjacent, falling, above);
+fn common_dominator(idom: &[usize], depth: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        if depth[a] >= depth[b] {
+            a = idom[a];
+        } else {
+            b = idom[b];
         }
     }
+    a
 }
 
 #[cfg(test)]
@@ -101,4 +155,19 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(7));
     }
+
+    #[test]
+    fn test_support_graph() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let mut bricks = parse_input(&input).unwrap();
+        let graph = SupportGraph::settle(&mut bricks);
+        assert_eq!(graph.supports(0), [1, 2]);
+        assert_eq!(graph.supported_by(3), [1, 2]);
+        assert_eq!(graph.supported_by(5), [3, 4]);
+        assert!(graph.supported_by(0).is_empty());
+        assert_eq!(bricks[6], (1, 1, 5, 1, 1, 6));
+        assert_eq!(graph.chain_reactions(), [6, 0, 0, 0, 0, 1, 0]);
+        let safe = (0..graph.len()).filter(|&b| graph.is_safe(b)).collect_vec();
+        assert_eq!(safe, [1, 2, 3, 4, 6]);
+    }
 }
@@ -1,140 +1,134 @@
-use std::collections::HashMap;
+use std::thread;
 
+use advent_of_code::maze::JunctionGraph;
+use advent_of_code::parse;
 use itertools::Itertools;
 
 advent_of_code::solution!(23);
 
+/// How many branching levels of the search are spread across threads.
+const FAN_OUT_DEPTH: usize = 3;
+
 pub fn part_one(input: &str) -> Option<usize> {
-    solve(input)
+    solve(input, true)
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
-    let desloped_input = input.replace(|c| ['<', '>', 'v', '^'].contains(&c), ".");
-    solve(desloped_input.as_str())
+    solve(input, false)
 }
 
-fn parse_input(
-    input: &str,
-) -> (
-    HashMap<(usize, usize), Vec<(usize, usize, usize)>>,
-    Vec<Vec<char>>,
-) {
-    let grid = input
-        .trim_end()
-        .lines()
-        .map(|l: &str| l.chars().collect_vec())
-        .collect_vec();
-
-    let mut graph = HashMap::<_, Vec<_>>::new();
-    for (row, col) in (0..grid.len()).cartesian_product(0..grid[0].len()) {
-        let neighbors = match grid[row][col] {
-            '#' => continue,
-            '.' => vec![(-1, 0), (0, 1), (1, 0), (0, -1)],
-            '>' => vec![(0, 1)],
-            '<' => vec![(0, -1)],
-            'v' => vec![(1, 0)],
-            '^' => vec![(-1, 0)],
-            _ => unreachable!(),
-        };
-        let entry = graph.entry((row, col)).or_insert(vec![]);
-        for (delta_row, delta_col) in neighbors {
-            let (new_row, new_col) = (
-                (row as isize + delta_row) as usize,
-                (col as isize + delta_col) as usize,
-            );
-            if grid
-                .get(new_row)
-                .and_then(|row| row.get(new_col))
-                .is_some_and(|&t| t != '#')
-            {
-                entry.push((new_row, new_col, 1));
-            }
-        }
+fn solve(input: &str, one_way: bool) -> Option<usize> {
+    let grid = parse::report(parse::grid(input))?;
+    let graph = JunctionGraph::new(&grid, &[], one_way);
+    let start = graph.index_of((0, 1))?;
+    let mut goal = graph.index_of((grid.len() - 1, grid[0].len() - 2))?;
+
+    // Once the only junction leading to the goal is reached, any other turn would
+    // cut the goal off, so the search can stop there and add the final edge.
+    let mut goal_dist = 0;
+    if let Ok((last, dist)) = (0..graph.len())
+        .filter_map(|node| {
+            let &(_, dist) = graph.edges(node).iter().find(|&&(next, _)| next == goal)?;
+            Some((node, dist))
+        })
+        .exactly_one()
+    {
+        goal = last;
+        goal_dist = dist;
     }
 
-    (graph, grid)
+    let dist = if graph.len() <= u64::BITS as usize {
+        longest_path::<u64>(graph.adjacency(), start, goal)
+    } else {
+        longest_path::<Vec<u64>>(graph.adjacency(), start, goal)
+    };
+    dist.map(|dist| dist + goal_dist)
 }
 
-fn solve(input: &str) -> Option<usize> {
-    let (mut graph, grid) = parse_input(input);
-
-    contract_corridors(&mut graph);
+/// A set of visited junctions, stored as one bit per node.
+trait Visited: Clone + Send + Sync {
+    fn empty(nodes: usize) -> Self;
+    fn contains(&self, node: usize) -> bool;
+    fn with(&self, node: usize) -> Self;
+}
 
-    let indexes = graph
-        .keys()
-        .enumerate()
-        .map(|(i, &pos)| (pos, i))
-        .collect::<HashMap<_, _>>();
+/// Fits graphs of up to 64 junctions, which covers the puzzle inputs.
+impl Visited for u64 {
+    fn empty(_: usize) -> Self {
+        0
+    }
 
-    let start = indexes[&(0, 1)];
-    let goal = indexes[&(grid.len() - 1, grid[0].len() - 2)];
-    let mut seen = vec![false; graph.len()];
+    fn contains(&self, node: usize) -> bool {
+        self & (1 << node) != 0
+    }
 
-    dfs(&index_graph(graph, indexes), &mut seen, goal, start)
+    fn with(&self, node: usize) -> Self {
+        self | 1 << node
+    }
 }
 
-fn contract_corridors(graph: &mut HashMap<(usize, usize), Vec<(usize, usize, usize)>>) {
-    let corridors = graph
-        .iter()
-        .filter(|(_, n)| n.len() == 2)
-        .map(|(&node, _)| node)
-        .collect::<Vec<_>>();
-    for (row, col) in corridors {
-        let neighbors = graph.remove(&(row, col)).unwrap();
-        let (row_1, col_1, dist_1) = neighbors[0];
-        let (row_2, col_2, dist_2) = neighbors[1];
-        let point_1 = graph.get_mut(&(row_1, col_1)).unwrap();
-        if let Some(i) = point_1
-            .iter()
-            .position(|&(row_p1, col_p1, _)| (row_p1, col_p1) == (row, col))
-        {
-            point_1[i] = (row_2, col_2, dist_1 + dist_2);
-        }
-        let point_2 = graph.get_mut(&(row_2, col_2)).unwrap();
-        if let Some(i) = point_2
-            .iter()
-            .position(|&(row_p2, col_p2, _)| (row_p2, col_p2) == (row, col))
-        {
-            point_2[i] = (row_1, col_1, dist_1 + dist_2);
-        }
+/// Fits graphs of any size, at the cost of a copy per step.
+impl Visited for Vec<u64> {
+    fn empty(nodes: usize) -> Self {
+        vec![0; nodes.div_ceil(u64::BITS as usize)]
     }
-}
 
-fn index_graph(
-    graph: HashMap<(usize, usize), Vec<(usize, usize, usize)>>,
-    indexes: HashMap<(usize, usize), usize>,
-) -> Vec<Vec<(usize, usize)>> {
-    let mut index_graph = vec![Vec::new(); graph.len()];
-    for (pos, neighbors) in &graph {
-        index_graph[indexes[pos]] = neighbors
-            .iter()
-            .map(|&(r, c, d)| (indexes[&(r, c)], d))
-            .collect();
+    fn contains(&self, node: usize) -> bool {
+        self[node / 64] & (1 << (node % 64)) != 0
     }
 
-    index_graph
+    fn with(&self, node: usize) -> Self {
+        let mut seen = self.clone();
+        seen[node / 64] |= 1 << (node % 64);
+        seen
+    }
 }
 
-fn dfs(
-    graph: &Vec<Vec<(usize, usize)>>,
-    seen: &mut Vec<bool>,
+fn longest_path<V: Visited>(
+    graph: &[Vec<(usize, usize)>],
+    start: usize,
+    goal: usize,
+) -> Option<usize> {
+    let seen = V::empty(graph.len()).with(start);
+    dfs(graph, goal, start, seen, FAN_OUT_DEPTH)
+}
+
+fn dfs<V: Visited>(
+    graph: &[Vec<(usize, usize)>],
     goal: usize,
     curr: usize,
+    seen: V,
+    fan_out: usize,
 ) -> Option<usize> {
     if curr == goal {
         return Some(0);
     }
-    let mut max_dist = None;
-    for &(next, d) in &graph[curr] {
-        if !seen[next] {
-            seen[next] = true;
-            if let Some(dist) = dfs(graph, seen, goal, next) {
-                max_dist = Some(max_dist.unwrap_or(0).max(d + dist))
-            }
-            seen[next] = false;
-        }
+    let branches = graph[curr]
+        .iter()
+        .filter(|&&(next, _)| !seen.contains(next));
+    let search = &|&(next, d): &(usize, usize)| {
+        dfs(
+            graph,
+            goal,
+            next,
+            seen.with(next),
+            fan_out.saturating_sub(1),
+        )
+        .map(|dist| d + dist)
+    };
+
+    if fan_out == 0 {
+        branches.filter_map(search).max()
+    } else {
+        thread::scope(|scope| {
+            branches
+                .map(|edge| scope.spawn(move || search(edge)))
+                .collect_vec()
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .max()
+        })
     }
-    max_dist
 }
 
 #[cfg(test)]
@@ -152,4 +146,14 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(154));
     }
+
+    #[test]
+    fn test_wide_visited_set() {
+        let grid = parse::grid(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        let graph = JunctionGraph::new(&grid, &[], false);
+        let start = graph.index_of((0, 1)).unwrap();
+        let goal = graph.index_of((grid.len() - 1, grid[0].len() - 2)).unwrap();
+        let dist = longest_path::<Vec<u64>>(graph.adjacency(), start, goal);
+        assert_eq!(dist, Some(154));
+    }
 }
@@ -1,57 +1,152 @@
+use advent_of_code::parse::{self, ParseError};
 use itertools::Itertools;
+use num::{BigInt, BigRational, Signed, ToPrimitive, Zero};
 
 advent_of_code::solution!(24);
 
+#[derive(Debug, Clone, Copy)]
+struct Hailstone {
+    pos: [i64; 3],
+    vel: [i64; 3],
+}
+
 pub fn part_one(input: &str) -> Option<usize> {
-    let hailstones = parse_input(input);
+    let hailstones = parse::report(parse_input(input))?;
     Some(x_y_intersections(
-        hailstones,
-        200_000_000_000_000.0,
-        400_000_000_000_000.0,
+        &hailstones,
+        200_000_000_000_000,
+        400_000_000_000_000,
     ))
 }
 
-pub fn part_two(_input: &str) -> Option<usize> {
-    None
+pub fn part_two(input: &str) -> Option<usize> {
+    let hailstones = parse::report(parse_input(input))?;
+    let rock = rock_position(&hailstones)?;
+    rock.iter().sum::<BigRational>().to_integer().to_usize()
 }
 
-fn parse_input(input: &str) -> Vec<(f64, f64, f64, f64, f64, f64)> {
-    input
-        .trim_end()
-        .lines()
-        .map(|l| {
-            l.split(['@', ','])
-                .map(|w| w.trim().parse().unwrap())
-                .collect_tuple()
-                .unwrap()
+fn parse_input(input: &str) -> Result<Vec<Hailstone>, ParseError> {
+    parse::each_line(input.trim_end(), |l| {
+        let [x, y, z, dx, dy, dz] = parse::exactly(l, parse::ints(l)?)?;
+        Ok(Hailstone {
+            pos: [x, y, z],
+            vel: [dx, dy, dz],
         })
-        .collect()
+    })
 }
 
-fn x_y_intersections(
-    hailstones: Vec<(f64, f64, f64, f64, f64, f64)>,
-    min_bound: f64,
-    max_bound: f64,
-) -> usize {
+fn x_y_intersections(hailstones: &[Hailstone], min_bound: i64, max_bound: i64) -> usize {
+    let bounds =
+        BigRational::from_integer(min_bound.into())..=BigRational::from_integer(max_bound.into());
     hailstones
         .iter()
         .tuple_combinations()
-        .filter(|(&(x1, y1, _, dx1, dy1, _), &(x2, y2, _, dx2, dy2, _))| {
-            let m1 = dy1 / dx1;
-            let m2 = dy2 / dx2;
-            if (m2 - m1).abs() <= f64::EPSILON {
-                return false;
-            }
-            let x = (m1 * x1 - m2 * x2 + y2 - y1) / (m1 - m2);
-            let y = (m1 * m2 * (x2 - x1) + m2 * y1 - m1 * y2) / (m2 - m1);
-            if dx1.signum() != (x - x1).signum() || dx2.signum() != (x - x2).signum() {
-                return false;
-            }
-            [x, y].iter().all(|v| (min_bound..=max_bound).contains(v))
-        })
+        .filter_map(|(a, b)| future_x_y_crossing(a, b))
+        .filter(|(x, y)| bounds.contains(x) && bounds.contains(y))
         .count()
 }
 
+/// Returns where the x/y paths of two hailstones cross, if both reach that point at
+/// or after time zero. Parallel paths, including identical ones, never cross.
+fn future_x_y_crossing(a: &Hailstone, b: &Hailstone) -> Option<(BigRational, BigRational)> {
+    let det = BigInt::from(b.vel[0]) * a.vel[1] - BigInt::from(a.vel[0]) * b.vel[1];
+    if det.is_zero() {
+        return None;
+    }
+    let (dx, dy) = (b.pos[0] - a.pos[0], b.pos[1] - a.pos[1]);
+    let t = BigRational::new(
+        BigInt::from(b.vel[0]) * dy - BigInt::from(b.vel[1]) * dx,
+        det.clone(),
+    );
+    let s = BigRational::new(
+        BigInt::from(a.vel[0]) * dy - BigInt::from(a.vel[1]) * dx,
+        det,
+    );
+    if t.is_negative() || s.is_negative() {
+        return None;
+    }
+    let at = |i: usize| BigRational::from_integer(a.pos[i].into()) + &t * BigInt::from(a.vel[i]);
+    Some((at(0), at(1)))
+}
+
+/// Finds the starting position of a rock thrown in a straight line that hits every hailstone.
+///
+/// For the rock `(P, V)` and each hailstone `(p, v)`, `(P - p) × (V - v) = 0`. Subtracting
+/// that equation for two hailstones cancels the `P × V` term, leaving three equations
+/// that are linear in `P` and `V`. Two pairs give six equations for the six unknowns.
+fn rock_position(hailstones: &[Hailstone]) -> Option<[BigRational; 3]> {
+    hailstones.iter().tuple_windows().find_map(|(a, b, c)| {
+        let rows = [(a, b), (a, c)]
+            .into_iter()
+            .flat_map(|(i, j)| pair_equations(i, j))
+            .collect();
+        let solution = solve_linear(rows)?;
+        let rock = [0, 1, 2].map(|axis| solution[axis].clone());
+        rock.iter().all(|v| v.is_integer()).then_some(rock)
+    })
+}
+
+/// Builds the rows `P × (v_i - v_j) + (p_i - p_j) × V = p_i × v_i - p_j × v_j`, each as
+/// coefficients of `[Px, Py, Pz, Vx, Vy, Vz]` followed by the constant.
+fn pair_equations(i: &Hailstone, j: &Hailstone) -> [Vec<BigRational>; 3] {
+    let dv = [0, 1, 2].map(|a| i.vel[a] - j.vel[a]);
+    let dp = [0, 1, 2].map(|a| i.pos[a] - j.pos[a]);
+    let (ci, cj) = (cross(i.pos, i.vel), cross(j.pos, j.vel));
+    let c = [0, 1, 2].map(|a| ci[a] - cj[a]);
+    [
+        [0, dv[2], -dv[1], 0, -dp[2], dp[1]],
+        [-dv[2], 0, dv[0], dp[2], 0, -dp[0]],
+        [dv[1], -dv[0], 0, -dp[1], dp[0], 0],
+    ]
+    .into_iter()
+    .zip(c)
+    .map(|(coefficients, c)| {
+        coefficients
+            .into_iter()
+            .map(i128::from)
+            .chain([c])
+            .map(|v| BigRational::from_integer(v.into()))
+            .collect()
+    })
+    .collect_vec()
+    .try_into()
+    .unwrap()
+}
+
+fn cross(a: [i64; 3], b: [i64; 3]) -> [i128; 3] {
+    let [a, b] = [a, b].map(|v| v.map(i128::from));
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Solves a square system given as augmented rows by Gauss-Jordan elimination,
+/// returning [`None`] if it has no unique solution.
+fn solve_linear(mut rows: Vec<Vec<BigRational>>) -> Option<Vec<BigRational>> {
+    let n = rows.len();
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| !rows[r][col].is_zero())?;
+        rows.swap(col, pivot);
+        let pivot_row = rows[col].clone();
+        for (r, row) in rows.iter_mut().enumerate() {
+            if r != col && !row[col].is_zero() {
+                let factor = &row[col] / &pivot_row[col];
+                for (v, p) in row.iter_mut().zip(&pivot_row) {
+                    *v -= &factor * p;
+                }
+            }
+        }
+    }
+    Some(
+        rows.iter()
+            .enumerate()
+            .map(|(r, row)| &row[n] / &row[r])
+            .collect(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,14 +154,14 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = &advent_of_code::template::read_file("examples", DAY);
-        let intersections = x_y_intersections(parse_input(input), 7.0, 27.0);
+        let intersections = x_y_intersections(&parse_input(input).unwrap(), 7, 27);
 
         assert_eq!(intersections, 2);
     }
 
-    // #[test]
-    // fn test_part_two() {
-    //     let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-    //     assert_eq!(result, Some(47));
-    // }
+    #[test]
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(47));
+    }
 }
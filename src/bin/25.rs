@@ -1,57 +1,217 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
 
+use advent_of_code::parse;
 use petgraph::{Graph, Undirected};
 use rustworkx_core::connectivity::stoer_wagner_min_cut;
 
 advent_of_code::solution!(25);
 
+/// How many wires the puzzle says must be cut to split the components in two.
+const EXPECTED_CUT: usize = 3;
+
 pub fn part_one(input: &str) -> Option<usize> {
-    let graph = parse_input(input);
-    if let Some((cut, part)) = stoer_wagner_min_cut(&graph, |_| Ok::<usize, usize>(1)).unwrap() {
-        assert!(cut == 3);
-        let groups = vec![part.len(), (graph.node_count() - part.len())];
-        Some(groups.iter().product())
-    } else {
-        None
-    }
+    let wiring = Wiring::new(input);
+    let cut = parse::report(wiring.min_cut(Algorithm::EdmondsKarp, EXPECTED_CUT))?;
+    Some(cut.partitions.iter().map(Vec::len).product())
 }
 
 pub fn part_two(_input: &str) -> Option<usize> {
     None
 }
 
-fn parse_input(input: &str) -> Graph<String, i32, Undirected> {
-    let h: HashMap<String, Vec<String>> = input
-        .trim_end()
-        .lines()
-        .map(|l| {
-            let (parent, children) = l.split_once(": ").unwrap();
-            (
-                parent.to_string(),
-                children
-                    .split_ascii_whitespace()
-                    .map(|c| c.to_string())
-                    .collect(),
-            )
+/// How [`Wiring::min_cut`] finds the cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// A global minimum cut, found by `rustworkx_core`.
+    StoerWagner,
+    /// Unit-capacity max flow from one component to each other, keeping the smallest cut.
+    EdmondsKarp,
+}
+
+/// The wires to cut and the two groups of components left.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cut<'a> {
+    pub wires: Vec<(&'a str, &'a str)>,
+    pub partitions: [Vec<&'a str>; 2],
+}
+
+/// Why no cut of the expected size was found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CutError {
+    /// The components are already in more than one group.
+    Disconnected,
+    /// The smallest cut has a different number of wires than expected.
+    WrongSize { expected: usize, found: usize },
+    /// Every cut needs more wires than expected.
+    TooConnected { expected: usize },
+}
+
+impl Display for CutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CutError::Disconnected => write!(f, "the components are not all connected"),
+            CutError::WrongSize { expected, found } => {
+                write!(
+                    f,
+                    "expected to cut {expected} wires, but {found} are enough"
+                )
+            }
+            CutError::TooConnected { expected } => {
+                write!(f, "no {expected} wires split the components in two")
+            }
+        }
+    }
+}
+
+/// The components, numbered in order of appearance, and the wires between them.
+pub struct Wiring<'a> {
+    names: Vec<&'a str>,
+    wires: Vec<(usize, usize)>,
+}
+
+impl<'a> Wiring<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let mut names = vec![];
+        let mut indexes = HashMap::new();
+        let mut index = |name: &'a str| {
+            *indexes.entry(name).or_insert_with(|| {
+                names.push(name);
+                names.len() - 1
+            })
+        };
+        let mut wires = vec![];
+        for line in input.trim_end().lines() {
+            let (parent, children) = line.split_once(": ").unwrap();
+            let parent = index(parent);
+            for child in children.split_ascii_whitespace() {
+                wires.push((parent, index(child)));
+            }
+        }
+        Self { names, wires }
+    }
+
+    /// Finds the minimum cut with `algorithm`, which must have exactly `expected` wires.
+    pub fn min_cut(&self, algorithm: Algorithm, expected: usize) -> Result<Cut<'a>, CutError> {
+        let side = match algorithm {
+            Algorithm::StoerWagner => self.stoer_wagner(expected)?,
+            Algorithm::EdmondsKarp => self.edmonds_karp(expected)?,
+        };
+        let wires = self
+            .wires
+            .iter()
+            .filter(|&&(a, b)| side.contains(&a) != side.contains(&b))
+            .map(|&(a, b)| (self.names[a], self.names[b]))
+            .collect();
+        let (inside, outside) = (0..self.names.len()).partition::<Vec<_>, _>(|n| side.contains(n));
+        Ok(Cut {
+            wires,
+            partitions: [inside, outside].map(|p| p.into_iter().map(|n| self.names[n]).collect()),
         })
-        .collect();
-
-    let mut nodes = HashMap::new();
-    h.iter().fold(
-        Graph::<String, i32, Undirected>::new_undirected(),
-        |mut graph, (k, children)| {
-            let src = *nodes
-                .entry(k)
-                .or_insert_with(|| graph.add_node(k.to_string()));
-            for child in children {
-                let dst = *nodes
-                    .entry(child)
-                    .or_insert_with(|| graph.add_node(child.to_string()));
-                graph.add_edge(src, dst, 1);
+    }
+
+    fn stoer_wagner(&self, expected: usize) -> Result<HashSet<usize>, CutError> {
+        let mut graph = Graph::<usize, (), Undirected>::new_undirected();
+        let nodes = (0..self.names.len())
+            .map(|n| graph.add_node(n))
+            .collect::<Vec<_>>();
+        for &(a, b) in &self.wires {
+            graph.add_edge(nodes[a], nodes[b], ());
+        }
+        let Ok(Some((cut, part))) = stoer_wagner_min_cut(&graph, |_| Ok::<usize, ()>(1)) else {
+            return Err(CutError::TooConnected { expected });
+        };
+        match cut {
+            0 => Err(CutError::Disconnected),
+            cut if cut != expected => Err(CutError::WrongSize {
+                expected,
+                found: cut,
+            }),
+            _ => Ok(part.into_iter().map(|node| graph[node]).collect()),
+        }
+    }
+
+    /// Returns the components on component `0`'s side of the minimum cut over all sinks.
+    fn edmonds_karp(&self, expected: usize) -> Result<HashSet<usize>, CutError> {
+        let mut adjacent = vec![vec![]; self.names.len()];
+        for (wire, &(a, b)) in self.wires.iter().enumerate() {
+            adjacent[a].push((b, wire));
+            adjacent[b].push((a, wire));
+        }
+        let mut best: Option<(usize, HashSet<usize>)> = None;
+        for sink in 1..self.names.len() {
+            // The flow through each wire, positive from its first component to its second.
+            let mut flow = vec![0i8; self.wires.len()];
+            let mut paths = 0;
+            let reached = loop {
+                if best.as_ref().is_some_and(|&(cut, _)| paths >= cut) {
+                    break None;
+                }
+                let (reached, path) = self.augmenting_path(&adjacent, &flow, sink);
+                let Some(path) = path else {
+                    break Some(reached);
+                };
+                for (wire, forward) in path {
+                    flow[wire] += if forward { 1 } else { -1 };
+                }
+                paths += 1;
+            };
+            if let Some(reached) = reached {
+                let side = (0..self.names.len()).filter(|&n| reached[n]).collect();
+                best = Some((paths, side));
+            }
+        }
+        match best {
+            None => Err(CutError::TooConnected { expected }),
+            Some((0, _)) => Err(CutError::Disconnected),
+            Some((cut, _)) if cut != expected => Err(CutError::WrongSize {
+                expected,
+                found: cut,
+            }),
+            Some((_, side)) => Ok(side),
+        }
+    }
+
+    /// Searches for a path from component `0` to `sink`, returning the components reached.
+    fn augmenting_path(
+        &self,
+        adjacent: &[Vec<(usize, usize)>],
+        flow: &[i8],
+        sink: usize,
+    ) -> (Vec<bool>, Option<Vec<(usize, bool)>>) {
+        let mut prev = vec![None; self.names.len()];
+        let mut reached = vec![false; self.names.len()];
+        reached[0] = true;
+        let mut queue = VecDeque::from([0]);
+        'search: while let Some(node) = queue.pop_front() {
+            for &(next, wire) in &adjacent[node] {
+                let forward = self.wires[wire].0 == node;
+                let spare = if forward {
+                    flow[wire] < 1
+                } else {
+                    flow[wire] > -1
+                };
+                if spare && !reached[next] {
+                    reached[next] = true;
+                    prev[next] = Some((node, wire, forward));
+                    if next == sink {
+                        break 'search;
+                    }
+                    queue.push_back(next);
+                }
             }
-            graph
-        },
-    )
+        }
+        if !reached[sink] {
+            return (reached, None);
+        }
+        let mut path = vec![];
+        let mut node = sink;
+        while let Some((from, wire, forward)) = prev[node] {
+            path.push((wire, forward));
+            node = from;
+        }
+        (reached, Some(path))
+    }
 }
 
 #[cfg(test)]
@@ -63,4 +223,44 @@ mod tests {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(54));
     }
+
+    #[test]
+    fn test_cut_algorithms() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let wiring = Wiring::new(&input);
+        let mut wires = vec![("hfx", "pzl"), ("bvb", "cmg"), ("jqt", "nvd")];
+        wires.sort();
+        for algorithm in [Algorithm::StoerWagner, Algorithm::EdmondsKarp] {
+            let mut cut = wiring.min_cut(algorithm, EXPECTED_CUT).unwrap();
+            cut.wires = cut
+                .wires
+                .iter()
+                .map(|&(a, b)| (a.min(b), a.max(b)))
+                .collect();
+            cut.wires.sort();
+            assert_eq!(cut.wires, wires);
+            let mut sizes = cut.partitions.map(|p| p.len());
+            sizes.sort();
+            assert_eq!(sizes, [6, 9]);
+        }
+        for expected in [2, 4] {
+            for algorithm in [Algorithm::StoerWagner, Algorithm::EdmondsKarp] {
+                assert_eq!(
+                    wiring.min_cut(algorithm, expected),
+                    Err(CutError::WrongSize { expected, found: 3 })
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cut_ignores_component_order() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let reversed = input.lines().rev().collect::<Vec<_>>().join("\n");
+        let wiring = Wiring::new(&reversed);
+        for algorithm in [Algorithm::StoerWagner, Algorithm::EdmondsKarp] {
+            let cut = wiring.min_cut(algorithm, EXPECTED_CUT).unwrap();
+            assert_eq!(cut.wires.len(), EXPECTED_CUT);
+        }
+    }
 }
@@ -1,4 +1,11 @@
 mod day;
+pub mod matcher;
+pub mod math;
+pub mod maze;
+pub mod nonogram;
+pub mod parse;
+pub mod polygon;
+pub mod ranges;
 pub mod template;
 
 pub use day::*;
@@ -20,6 +20,7 @@ mod args {
             day: Day,
             release: bool,
             time: bool,
+            debug: bool,
             submit: Option<u8>,
         },
         All {
@@ -50,6 +51,7 @@ mod args {
                 release: args.contains("--release"),
                 submit: args.opt_value_from_str("--submit")?,
                 time: args.contains("--time"),
+                debug: args.contains("--debug"),
             },
             Some(x) => {
                 eprintln!("Unknown command: {x}");
@@ -85,8 +87,9 @@ fn main() {
                 day,
                 release,
                 time,
+                debug,
                 submit,
-            } => solve::handle(day, release, time, submit),
+            } => solve::handle(day, release, time, debug, submit),
         },
     };
 }
@@ -0,0 +1,158 @@
+//! Finding every occurrence of a fixed set of tokens in text in one pass, overlapping
+//! ones included, with an Aho–Corasick automaton.
+//!
+//! See <https://en.wikipedia.org/wiki/Aho%E2%80%93Corasick_algorithm>.
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+/// An occurrence of a token in the text searched, as the byte range it covers and the
+/// value the token stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<T> {
+    pub start: usize,
+    pub end: usize,
+    pub value: T,
+}
+
+/// An automaton recognising a vocabulary of tokens, each standing for a value.
+///
+/// State 0 is the empty prefix, and every other state a prefix of some token. `next`
+/// holds the full transition table, with failure links already followed, so each byte of
+/// text costs one lookup. `outputs` lists the tokens ending at each state, longest first,
+/// including those that are suffixes of its prefix.
+#[derive(Debug, Clone)]
+pub struct Matcher<T> {
+    tokens: Vec<(usize, T)>,
+    next: Vec<[usize; 256]>,
+    outputs: Vec<Vec<usize>>,
+}
+
+impl<T: Copy> Matcher<T> {
+    /// Builds a matcher for `vocabulary`, a list of tokens and the values they stand for.
+    /// Empty tokens never match.
+    pub fn new<'a>(vocabulary: impl IntoIterator<Item = (&'a str, T)>) -> Self {
+        let mut matcher = Self {
+            tokens: vec![],
+            next: vec![[0; 256]],
+            outputs: vec![vec![]],
+        };
+        for (token, value) in vocabulary {
+            if token.is_empty() {
+                continue;
+            }
+            let mut state = 0;
+            for b in token.bytes() {
+                if matcher.next[state][b as usize] == 0 {
+                    matcher.next.push([0; 256]);
+                    matcher.outputs.push(vec![]);
+                    matcher.next[state][b as usize] = matcher.next.len() - 1;
+                }
+                state = matcher.next[state][b as usize];
+            }
+            matcher.outputs[state].push(matcher.tokens.len());
+            matcher.tokens.push((token.len(), value));
+        }
+
+        // Visit states in order of depth, so each failure state is finished before it is
+        // needed. A state's table holds only its trie edges until it is visited.
+        let mut fail = vec![0; matcher.next.len()];
+        let mut queue = VecDeque::from([0]);
+        while let Some(state) = queue.pop_front() {
+            for b in 0..256 {
+                let child = matcher.next[state][b];
+                if state == 0 {
+                    if child != 0 {
+                        queue.push_back(child);
+                    }
+                } else if child != 0 {
+                    fail[child] = matcher.next[fail[state]][b];
+                    let inherited = matcher.outputs[fail[child]].clone();
+                    matcher.outputs[child].extend(inherited);
+                    queue.push_back(child);
+                } else {
+                    matcher.next[state][b] = matcher.next[fail[state]][b];
+                }
+            }
+        }
+        matcher
+    }
+
+    /// Returns every occurrence of a token in `text`, in order of where they end, and
+    /// longest first among those ending at the same place.
+    pub fn find_iter<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Match<T>> + 'a {
+        text.bytes()
+            .enumerate()
+            .scan(0, |state, (i, b)| {
+                *state = self.next[*state][b as usize];
+                Some((i + 1, *state))
+            })
+            .flat_map(move |(end, state)| {
+                self.outputs[state].iter().map(move |&token| {
+                    let (len, value) = self.tokens[token];
+                    Match {
+                        start: end - len,
+                        end,
+                        value,
+                    }
+                })
+            })
+    }
+
+    /// Returns the occurrences that start first and last in `text`, or [`None`] if no
+    /// token occurs. Of several starting at the same place, the longest is chosen.
+    pub fn first_last(&self, text: &str) -> Option<(Match<T>, Match<T>)> {
+        self.find_iter(text).fold(None, |found, m| {
+            let Some((first, last)) = found else {
+                return Some((m, m));
+            };
+            let first = if (m.start, Reverse(m.end)) < (first.start, Reverse(first.end)) {
+                m
+            } else {
+                first
+            };
+            let last = if (m.start, m.end) > (last.start, last.end) {
+                m
+            } else {
+                last
+            };
+            Some((first, last))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_tokens() {
+        let matcher = Matcher::new([("one", 1), ("two", 2), ("eight", 8), ("2", 2)]);
+        let found = matcher
+            .find_iter("xtwoneighttwo2")
+            .map(|m| (m.start, m.value))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(1, 2), (3, 1), (5, 8), (10, 2), (13, 2)]);
+
+        let (first, last) = matcher.first_last("eightwo").unwrap();
+        assert_eq!((first.value, last.value), (8, 2));
+        assert_eq!((first.start, first.end, last.start, last.end), (0, 5, 4, 7));
+    }
+
+    #[test]
+    fn test_nested_tokens() {
+        let matcher = Matcher::new([("abcd", 'L'), ("bc", 'S'), ("c", 'C'), ("", 'E')]);
+        let found = matcher.find_iter("abcd").collect::<Vec<_>>();
+        assert_eq!(found.iter().map(|m| m.value).collect::<String>(), "SCL");
+        let (first, last) = matcher.first_last("abcd").unwrap();
+        assert_eq!((first.value, last.value), ('L', 'C'));
+    }
+
+    #[test]
+    fn test_no_tokens() {
+        let matcher = Matcher::new([("1", 1), ("one", 1)]);
+        assert_eq!(matcher.first_last("treb7uchet"), None);
+        assert_eq!(matcher.first_last(""), None);
+        assert_eq!(Matcher::<u8>::new([]).first_last("one"), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Number theory and polynomial helpers shared by the days that combine cycles or
+//! extrapolate sequences. All arithmetic is checked and reports [`MathError::Overflow`]
+//! rather than wrapping.
+
+use std::fmt::Display;
+
+use num::rational::Ratio;
+use num::{CheckedAdd, CheckedMul, Zero};
+
+/// Why a calculation in this module has no answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    /// The congruences or points given are inconsistent.
+    NoSolution,
+    /// The answer, or a value needed to find it, does not fit the integer type used.
+    Overflow,
+    /// The answer is a fraction where an integer was expected.
+    NotInteger,
+}
+
+impl Display for MathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MathError::NoSolution => write!(f, "no solution exists"),
+            MathError::Overflow => write!(f, "the calculation overflowed"),
+            MathError::NotInteger => write!(f, "the result is not an integer"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+/// Returns `(g, x, y)` with `a * x + b * y = g`, where `g` is the non-negative greatest
+/// common divisor of `a` and `b`.
+pub fn egcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// Returns the least common multiple of `a` and `b`.
+pub fn lcm(a: u64, b: u64) -> Result<u64, MathError> {
+    if a == 0 || b == 0 {
+        return Ok(0);
+    }
+    let (g, _, _) = egcd(a.into(), b.into());
+    (a / g as u64).checked_mul(b).ok_or(MathError::Overflow)
+}
+
+/// Combines congruences `x = residue (mod modulus)` into one, `x = r (mod m)`, with
+/// `0 <= r < m`. The moduli need not be coprime.
+///
+/// # Panics
+///
+/// Panics if any modulus is zero.
+pub fn crt(congruences: impl IntoIterator<Item = (u64, u64)>) -> Result<(u64, u64), MathError> {
+    congruences
+        .into_iter()
+        .try_fold((0, 1), |(a1, m1), (a2, m2)| {
+            assert!(m2 > 0, "a congruence needs a positive modulus");
+            let (g, p, _) = egcd(m1.into(), m2.into());
+            let diff = i128::from(a2 % m2) - i128::from(a1);
+            if diff % g != 0 {
+                return Err(MathError::NoSolution);
+            }
+            let modulus = (m1 / g as u64).checked_mul(m2).ok_or(MathError::Overflow)?;
+            // Both factors are below `m2 / g <= u64::MAX`, so their product fits a `u128`.
+            let step = i128::from(m2) / g;
+            let k =
+                ((diff / g).rem_euclid(step) as u128 * p.rem_euclid(step) as u128) % step as u128;
+            let x = (u128::from(a1) + u128::from(m1) * k) % u128::from(modulus);
+            Ok((x as u64, modulus))
+        })
+}
+
+/// Returns the first value shared by every sequence `offset, offset + period, ...`.
+///
+/// When every offset is zero, or equal to its period, this is the LCM of the periods.
+///
+/// # Panics
+///
+/// Panics if any period is zero.
+pub fn align(sequences: &[(u64, u64)]) -> Result<u64, MathError> {
+    let (residue, modulus) = crt(sequences.iter().copied())?;
+    let start = sequences
+        .iter()
+        .map(|&(offset, _)| offset)
+        .max()
+        .unwrap_or(0);
+    if residue >= start {
+        return Ok(residue);
+    }
+    (start - residue)
+        .div_ceil(modulus)
+        .checked_mul(modulus)
+        .and_then(|laps| laps.checked_add(residue))
+        .ok_or(MathError::Overflow)
+}
+
+/// Returns the binomial coefficient `C(n, k) = n (n - 1) ... (n - k + 1) / k!`, which is an
+/// integer for every integer `n`, including negative ones.
+pub fn binomial(n: i128, k: usize) -> Result<i128, MathError> {
+    let mut binomial = 1i128;
+    for j in 0..k as i128 {
+        // `C(n, j) * (n - j)` is `(j + 1) * C(n, j + 1)`, so the division is exact.
+        binomial = binomial.checked_mul(n - j).ok_or(MathError::Overflow)? / (j + 1);
+    }
+    Ok(binomial)
+}
+
+/// Returns the weight of each of `len` samples taken at `i = 0, 1, ...` in the value at `n`
+/// of the lowest degree polynomial through them.
+///
+/// These are the Lagrange basis polynomials at equally spaced points, which come out as
+/// `(-1)^(len - 1 - i) C(n, i) C(n - i - 1, len - 1 - i)`. The next term has weights
+/// `(-1)^(len - 1 - i) C(len, i)` and the one before the first `(-1)^i C(len, i + 1)`.
+fn sample_weights(len: usize, n: i64) -> Result<Vec<i128>, MathError> {
+    let n = i128::from(n);
+    (0..len)
+        .map(|i| {
+            let sign = if (len - 1 - i).is_multiple_of(2) {
+                1
+            } else {
+                -1
+            };
+            binomial(n, i)?
+                .checked_mul(binomial(n - i as i128 - 1, len - 1 - i)?)
+                .ok_or(MathError::Overflow)
+                .map(|weight| sign * weight)
+        })
+        .collect()
+}
+
+/// Evaluates at `n` the lowest degree polynomial through `values[i]` at `i = 0, 1, ...`,
+/// as a sum of the values weighted by products of binomial coefficients.
+pub fn extrapolate(values: &[i64], n: i64) -> Result<i64, MathError> {
+    let weights = sample_weights(values.len(), n)?;
+    let total = weights
+        .iter()
+        .zip(values)
+        .try_fold(0i128, |total, (&weight, &value)| {
+            weight
+                .checked_mul(value.into())
+                .and_then(|term| total.checked_add(term))
+        })
+        .ok_or(MathError::Overflow)?;
+    i64::try_from(total).map_err(|_| MathError::Overflow)
+}
+
+/// Evaluates at `x` the lowest degree polynomial through `points` using the Lagrange form.
+///
+/// Returns [`MathError::NoSolution`] if two points share an `x`, and
+/// [`MathError::NotInteger`] if the value at `x` is a fraction.
+pub fn lagrange_extrapolate(points: &[(i64, i64)], x: i64) -> Result<i64, MathError> {
+    let mut total = Ratio::<i128>::zero();
+    for (i, &(xi, yi)) in points.iter().enumerate() {
+        let mut term = Ratio::from_integer(yi.into());
+        for (j, &(xj, _)) in points.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            if xi == xj {
+                return Err(MathError::NoSolution);
+            }
+            let factor = Ratio::new(
+                i128::from(x) - i128::from(xj),
+                i128::from(xi) - i128::from(xj),
+            );
+            term = term.checked_mul(&factor).ok_or(MathError::Overflow)?;
+        }
+        total = total.checked_add(&term).ok_or(MathError::Overflow)?;
+    }
+    if !total.is_integer() {
+        return Err(MathError::NotInteger);
+    }
+    i64::try_from(total.to_integer()).map_err(|_| MathError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_egcd_and_lcm() {
+        assert_eq!(egcd(240, 46), (2, -9, 47));
+        assert_eq!(egcd(-4, 6), (2, 1, 1));
+        assert_eq!(lcm(4, 6), Ok(12));
+        assert_eq!(lcm(u64::MAX, u64::MAX - 1), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt([(2, 3), (3, 5), (2, 7)]), Ok((23, 105)));
+        assert_eq!(crt([(1, 4), (3, 6)]), Ok((9, 12)));
+        assert_eq!(crt([(0, 4), (1, 6)]), Err(MathError::NoSolution));
+        let big = [(1, u64::MAX), (0, u64::MAX - 1)];
+        assert_eq!(crt(big), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn test_align() {
+        assert_eq!(align(&[(2, 2), (3, 3), (5, 5)]), Ok(30));
+        assert_eq!(align(&[(5, 3), (1, 2)]), Ok(5));
+        assert_eq!(align(&[(11, 3), (0, 2)]), Ok(14));
+        assert_eq!(align(&[(1, 2), (2, 2)]), Err(MathError::NoSolution));
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        let squares = [0, 1, 4, 9];
+        assert_eq!(extrapolate(&squares, 10), Ok(100));
+        assert_eq!(extrapolate(&squares, -3), Ok(9));
+        assert_eq!(extrapolate(&[1, 3, 6, 10, 15, 21], 6), Ok(28));
+
+        assert_eq!(binomial(5, 2), Ok(10));
+        assert_eq!(binomial(-1, 3), Ok(-1));
+        assert_eq!(binomial(3, 5), Ok(0));
+        assert_eq!(sample_weights(3, 3), Ok(vec![1, -3, 3]));
+        assert_eq!(sample_weights(3, -1), Ok(vec![3, -3, 1]));
+        assert_eq!(sample_weights(3, 1), Ok(vec![0, 1, 0]));
+        let cubes = [0, 1, 8, 27, 64, 125];
+        for n in -20..20 {
+            assert_eq!(extrapolate(&cubes, n), Ok(n * n * n));
+        }
+        assert_eq!(extrapolate(&[], 3), Ok(0));
+        assert_eq!(extrapolate(&[0, i64::MAX / 2], 4), Err(MathError::Overflow));
+
+        assert_eq!(
+            lagrange_extrapolate(&[(1, 1), (3, 9), (4, 16)], 10),
+            Ok(100)
+        );
+        assert_eq!(
+            lagrange_extrapolate(&[(0, 0), (2, 1)], 1),
+            Err(MathError::NotInteger)
+        );
+        assert_eq!(
+            lagrange_extrapolate(&[(1, 1), (1, 2)], 0),
+            Err(MathError::NoSolution)
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Contraction of grid mazes into weighted graphs of junctions.
+
+use std::collections::HashMap;
+
+const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// A maze grid contracted to its junctions, dead ends and any explicitly kept cells.
+///
+/// Nodes are numbered densely in row-major order of their grid position, so a
+/// graph with at most 64 nodes can track visited sets as `1 << node` bits in a [`u64`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JunctionGraph {
+    positions: Vec<(usize, usize)>,
+    edges: Vec<Vec<(usize, usize)>>,
+    indexes: HashMap<(usize, usize), usize>,
+}
+
+impl JunctionGraph {
+    /// Contracts every corridor of `grid` into a single weighted edge.
+    ///
+    /// `#` tiles are walls and every other tile is open. When `one_way` is set, the
+    /// slope tiles `^`, `>`, `v` and `<` can only be left in the direction they point.
+    /// Cells listed in `keep` stay nodes even if they lie in the middle of a corridor.
+    pub fn new(grid: &[Vec<char>], keep: &[(usize, usize)], one_way: bool) -> Self {
+        let open = |(row, col): (usize, usize)| {
+            grid.get(row)
+                .and_then(|r| r.get(col))
+                .is_some_and(|&t| t != '#')
+        };
+        let neighbours = |pos: (usize, usize)| {
+            DIRECTIONS
+                .iter()
+                .filter_map(move |&delta| step(pos, delta))
+                .filter(move |&next| open(next))
+        };
+        let can_leave = |(row, col): (usize, usize), delta: (isize, isize)| {
+            !one_way
+                || match grid[row][col] {
+                    '^' => delta == (-1, 0),
+                    '>' => delta == (0, 1),
+                    'v' => delta == (1, 0),
+                    '<' => delta == (0, -1),
+                    _ => true,
+                }
+        };
+
+        let positions = (0..grid.len())
+            .flat_map(|row| (0..grid[row].len()).map(move |col| (row, col)))
+            .filter(|&pos| open(pos) && (keep.contains(&pos) || neighbours(pos).count() != 2))
+            .collect::<Vec<_>>();
+        let indexes = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &pos)| (pos, i))
+            .collect::<HashMap<_, _>>();
+
+        let edges = positions
+            .iter()
+            .map(|&from| {
+                DIRECTIONS
+                    .iter()
+                    .filter(|&&delta| can_leave(from, delta))
+                    .filter_map(|&delta| {
+                        let (mut prev, mut curr) = (from, step(from, delta).filter(|&p| open(p))?);
+                        let mut dist = 1;
+                        while !indexes.contains_key(&curr) {
+                            let next = neighbours(curr).find(|&next| next != prev)?;
+                            if !can_leave(curr, delta_between(curr, next)) {
+                                return None;
+                            }
+                            (prev, curr) = (curr, next);
+                            dist += 1;
+                        }
+                        Some((indexes[&curr], dist))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            positions,
+            edges,
+            indexes,
+        }
+    }
+
+    /// Returns the number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns `true` if the grid had no open cells.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the node at grid position `pos`, if that cell was kept as a node.
+    pub fn index_of(&self, pos: (usize, usize)) -> Option<usize> {
+        self.indexes.get(&pos).copied()
+    }
+
+    /// Returns the grid position of `node`.
+    pub fn position(&self, node: usize) -> (usize, usize) {
+        self.positions[node]
+    }
+
+    /// Returns the outgoing `(node, distance)` edges of `node`.
+    pub fn edges(&self, node: usize) -> &[(usize, usize)] {
+        &self.edges[node]
+    }
+
+    /// Returns the outgoing edges of every node, indexed by node.
+    pub fn adjacency(&self) -> &[Vec<(usize, usize)>] {
+        &self.edges
+    }
+}
+
+fn step(
+    (row, col): (usize, usize),
+    (delta_row, delta_col): (isize, isize),
+) -> Option<(usize, usize)> {
+    Some((
+        row.checked_add_signed(delta_row)?,
+        col.checked_add_signed(delta_col)?,
+    ))
+}
+
+fn delta_between(from: (usize, usize), to: (usize, usize)) -> (isize, isize) {
+    (
+        to.0 as isize - from.0 as isize,
+        to.1 as isize - from.1 as isize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::read_file;
+
+    fn example_grid() -> Vec<Vec<char>> {
+        read_file("examples", crate::day!(23))
+            .lines()
+            .map(|l| l.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_contract_one_way() {
+        let grid = example_grid();
+        let graph = JunctionGraph::new(&grid, &[], true);
+        let start = graph.index_of((0, 1)).unwrap();
+        let junction = graph.index_of((5, 3)).unwrap();
+        assert_eq!(graph.len(), 9);
+        assert_eq!(graph.edges(start), &[(junction, 15)]);
+        assert_eq!(graph.edges(junction).len(), 2);
+        assert!(graph.edges(graph.index_of((22, 21)).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_contract_two_way() {
+        let grid = example_grid();
+        let graph = JunctionGraph::new(&grid, &[], false);
+        let edge_count = graph.adjacency().iter().map(Vec::len).sum::<usize>();
+        assert_eq!(graph.len(), 9);
+        assert_eq!(edge_count, 2 * 12);
+    }
+
+    #[test]
+    fn test_keep_corridor_cell() {
+        let grid = example_grid();
+        let graph = JunctionGraph::new(&grid, &[(1, 1)], false);
+        let start = graph.index_of((0, 1)).unwrap();
+        let kept = graph.index_of((1, 1)).unwrap();
+        assert_eq!(graph.len(), 10);
+        assert_eq!(graph.edges(start), &[(kept, 1)]);
+    }
+}
@@ -0,0 +1,262 @@
+//! Counting and listing the ways a line of cells can hold runs of filled cells, as in
+//! nonogram clues or damaged spring records.
+
+use crate::math::MathError;
+
+/// A cell of a line, written `.`, `#` or `?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Empty,
+    Filled,
+    Unknown,
+}
+
+impl TryFrom<char> for Cell {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '.' => Ok(Cell::Empty),
+            '#' => Ok(Cell::Filled),
+            '?' => Ok(Cell::Unknown),
+            _ => Err(c),
+        }
+    }
+}
+
+/// Repeats `cells` and `runs` `factor` times, joining the copies of `cells` with an
+/// unknown cell.
+pub fn unfold(cells: &[Cell], runs: &[usize], factor: usize) -> (Vec<Cell>, Vec<usize>) {
+    let mut unfolded = Vec::with_capacity((cells.len() + 1) * factor);
+    for i in 0..factor {
+        if i > 0 {
+            unfolded.push(Cell::Unknown);
+        }
+        unfolded.extend_from_slice(cells);
+    }
+    (unfolded, runs.repeat(factor))
+}
+
+/// Solves a line of cells against the lengths of its runs of filled cells, in order.
+///
+/// A state `(i, j)` means cells `..i` hold exactly the first `j` runs and cell `i` may
+/// start a new run. `ways[i][j]` counts the ways to finish the line from each state, and
+/// `reach[i][j]` the ways to arrive at it, both filled iteratively. States off every
+/// arrangement can have more ways than fit in a [`u64`], so both saturate at [`u128::MAX`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineSolver {
+    cells: Vec<Cell>,
+    runs: Vec<usize>,
+    ways: Vec<Vec<u128>>,
+    reach: Vec<Vec<u128>>,
+}
+
+impl LineSolver {
+    /// Returns [`MathError::Overflow`] if the number of arrangements does not fit in a
+    /// [`u64`].
+    pub fn new(cells: &[Cell], runs: &[usize]) -> Result<Self, MathError> {
+        let (n, m) = (cells.len(), runs.len());
+        let mut solver = Self {
+            cells: cells.to_vec(),
+            runs: runs.to_vec(),
+            ways: vec![vec![0; m + 1]; n + 1],
+            reach: vec![vec![0; m + 1]; n + 1],
+        };
+        solver.ways[n][m] = 1;
+        for i in (0..n).rev() {
+            for j in (0..=m).rev() {
+                let mut ways = 0u128;
+                if cells[i] != Cell::Filled {
+                    ways = solver.ways[i + 1][j];
+                }
+                if let Some(next) = solver.place(i, j) {
+                    ways = ways.saturating_add(solver.ways[next][j + 1]);
+                }
+                solver.ways[i][j] = ways;
+            }
+        }
+        if solver.ways[0][0] > u64::MAX as u128 {
+            return Err(MathError::Overflow);
+        }
+        solver.reach[0][0] = 1;
+        for (i, &cell) in cells.iter().enumerate() {
+            for j in 0..=m {
+                let reach = solver.reach[i][j];
+                if reach == 0 {
+                    continue;
+                }
+                if cell != Cell::Filled {
+                    solver.add_reach(i + 1, j, reach);
+                }
+                if let Some(next) = solver.place(i, j) {
+                    solver.add_reach(next, j + 1, reach);
+                }
+            }
+        }
+        Ok(solver)
+    }
+
+    fn add_reach(&mut self, i: usize, j: usize, ways: u128) {
+        let reach = &mut self.reach[i][j];
+        *reach = reach.saturating_add(ways);
+    }
+
+    /// Returns the state after placing run `j` from cell `i`, if it fits there.
+    fn place(&self, i: usize, j: usize) -> Option<usize> {
+        let run = *self.runs.get(j)?;
+        let end = i + run;
+        let fits = end <= self.cells.len()
+            && self.cells[i..end].iter().all(|&c| c != Cell::Empty)
+            && self.cells.get(end) != Some(&Cell::Filled);
+        fits.then_some((end + 1).min(self.cells.len()))
+    }
+
+    /// Returns the number of arrangements of the runs that match the known cells.
+    pub fn count(&self) -> u64 {
+        self.ways[0][0] as u64
+    }
+
+    /// Returns the arrangement at `index` in a fixed order, as whether each cell is
+    /// filled, or [`None`] if `index` is at least [`LineSolver::count`]. Picking a random
+    /// index below the count samples arrangements uniformly.
+    pub fn arrangement(&self, mut index: u64) -> Option<Vec<bool>> {
+        if index >= self.count() {
+            return None;
+        }
+        let mut filled = vec![false; self.cells.len()];
+        let (mut i, mut j) = (0, 0);
+        while i < self.cells.len() {
+            if self.cells[i] != Cell::Filled {
+                let empty = self.ways[i + 1][j];
+                if (index as u128) < empty {
+                    i += 1;
+                    continue;
+                }
+                index -= empty as u64;
+            }
+            let next = self.place(i, j).unwrap();
+            filled[i..i + self.runs[j]].fill(true);
+            (i, j) = (next, j + 1);
+        }
+        Some(filled)
+    }
+
+    /// Returns every arrangement, in the order of [`LineSolver::arrangement`].
+    pub fn arrangements(&self) -> impl Iterator<Item = Vec<bool>> + '_ {
+        (0..self.count()).map(|index| self.arrangement(index).unwrap())
+    }
+
+    /// Returns, for each cell, whether every arrangement fills it (`Some(true)`), none
+    /// do (`Some(false)`), or it varies. Returns [`None`] if there are no arrangements.
+    pub fn forced(&self) -> Option<Vec<Option<bool>>> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let n = self.cells.len();
+        // How many arrangements fill each cell, as a difference array over run placements.
+        // Each product counts arrangements placing run `j` at `i`, so is at most `total`;
+        // a saturated `reach` only meets states with no ways to finish.
+        let mut starts = vec![0i128; n + 1];
+        for i in 0..n {
+            for j in 0..self.runs.len() {
+                if let Some(next) = self.place(i, j) {
+                    let through = self.reach[i][j].saturating_mul(self.ways[next][j + 1]) as i128;
+                    starts[i] += through;
+                    starts[i + self.runs[j]] -= through;
+                }
+            }
+        }
+        let mut filled = 0;
+        Some(
+            starts[..n]
+                .iter()
+                .map(|&delta| {
+                    filled += delta;
+                    match filled {
+                        0 => Some(false),
+                        f if f == total as i128 => Some(true),
+                        _ => None,
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solver(cells: &str, runs: &[usize]) -> LineSolver {
+        let cells = cells
+            .chars()
+            .map(|c| Cell::try_from(c).unwrap())
+            .collect::<Vec<_>>();
+        LineSolver::new(&cells, runs).unwrap()
+    }
+
+    #[test]
+    fn test_count_and_arrangements() {
+        let line = solver("?###????????", &[3, 2, 1]);
+        assert_eq!(line.count(), 10);
+        let all = line.arrangements().collect::<Vec<_>>();
+        assert_eq!(all.len(), 10);
+        assert!(all
+            .iter()
+            .all(|a| a.iter().filter(|&&f| f).count() == 6 && a[1]));
+        assert_eq!(
+            all.iter().collect::<std::collections::HashSet<_>>().len(),
+            10
+        );
+        assert_eq!(line.arrangement(10), None);
+        assert_eq!(solver("#.#", &[2]).count(), 0);
+    }
+
+    #[test]
+    fn test_forced() {
+        assert_eq!(
+            solver("?????", &[4]).forced(),
+            Some(vec![None, Some(true), Some(true), Some(true), None])
+        );
+        assert_eq!(
+            solver("???.###", &[1, 1, 3]).forced(),
+            Some(
+                [true, false, true, false, true, true, true]
+                    .map(Some)
+                    .to_vec()
+            )
+        );
+        assert_eq!(solver("#", &[]).forced(), None);
+    }
+
+    #[test]
+    fn test_unfold() {
+        let (cells, runs) = unfold(&[Cell::Filled, Cell::Empty], &[1], 3);
+        assert_eq!(cells.len(), 8);
+        assert_eq!(cells[2], Cell::Unknown);
+        assert_eq!(runs, [1, 1, 1]);
+        let line = LineSolver::new(&cells, &runs).unwrap();
+        assert_eq!(line.count(), 1);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let (cells, runs) = unfold(&[Cell::Unknown; 3], &[1], 40);
+        assert_eq!(LineSolver::new(&cells, &runs), Err(MathError::Overflow));
+
+        // Ways to place the first 40 runs overflow a u64, but the last run never fits.
+        let mut cells = vec![Cell::Unknown; 130];
+        cells.push(Cell::Empty);
+        let mut runs = vec![1; 40];
+        runs.push(100);
+        let line = LineSolver::new(&cells, &runs).unwrap();
+        assert!(line
+            .reach
+            .iter()
+            .flatten()
+            .any(|&reach| reach > u64::MAX as u128));
+        assert_eq!(line.count(), 0);
+        assert_eq!(line.forced(), None);
+    }
+}
@@ -0,0 +1,351 @@
+//! Small parsing helpers for puzzle inputs that report errors with their position.
+//!
+//! Every helper reports line and column numbers relative to the text it was given.
+//! [`each_block`], [`each_line`] and [`each_item`] shift those positions so that errors
+//! raised while parsing a piece of the input point at the right place in the whole
+//! input; [`ParseError::within`] does the same for any other subslice.
+
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Returns all integers in `s`, in order. A `-` directly before a digit is a sign.
+pub fn ints<T: FromStr>(s: &str) -> Result<Vec<T>, ParseError> {
+    let bytes = s.as_bytes();
+    let mut values = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let negative = bytes[i] == b'-' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+        if !negative && !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        values.push(parse_at(s, start, &s[start..i])?);
+    }
+    Ok(values)
+}
+
+/// Splits `input` into blocks separated by blank lines, skipping empty blocks.
+pub fn blocks(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .trim_end()
+        .split("\n\n")
+        .map(|block| block.trim_start_matches('\n'))
+        .filter(|block| !block.trim().is_empty())
+}
+
+/// Parses `input` into a rectangular grid of characters.
+pub fn grid(input: &str) -> Result<Vec<Vec<char>>, ParseError> {
+    let grid = input
+        .trim_end()
+        .lines()
+        .map(|l| l.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let width = grid.first().map_or(0, Vec::len);
+    match grid.iter().position(|row| row.len() != width) {
+        Some(row) => Err(ParseError::new(
+            row + 1,
+            grid[row].len().min(width) + 1,
+            ParseErrorKind::RaggedGrid {
+                expected: width,
+                found: grid[row].len(),
+            },
+        )),
+        None => Ok(grid),
+    }
+}
+
+/// Parses the whole of `s`, ignoring surrounding whitespace.
+pub fn value<T: FromStr>(s: &str) -> Result<T, ParseError> {
+    let text = s.trim();
+    parse_at(s, offset(s, text), text)
+}
+
+/// Splits `s` around the first occurrence of `separator`.
+pub fn key_value<'a>(s: &'a str, separator: &str) -> Result<(&'a str, &'a str), ParseError> {
+    s.split_once(separator).ok_or_else(|| {
+        let (line, column) = position(s, s.trim_end().len());
+        ParseError::new(
+            line,
+            column,
+            ParseErrorKind::MissingSeparator(separator.to_string()),
+        )
+    })
+}
+
+/// Splits the first character off `s`, which must be one of `options`, and returns
+/// its index in `options` with the rest of `s`.
+pub fn one_of<'a>(s: &'a str, options: &str) -> Result<(usize, &'a str), ParseError> {
+    let first = s.chars().next();
+    match first.and_then(|c| options.chars().position(|option| option == c)) {
+        Some(i) => Ok((i, &s[first.map_or(0, char::len_utf8)..])),
+        None => Err(ParseError::new(
+            1,
+            1,
+            ParseErrorKind::ExpectedOneOf(options.to_string()),
+        )),
+    }
+}
+
+/// Parses every `separator`-delimited item of `s`, ignoring surrounding whitespace.
+/// A whitespace `separator` splits on any run of whitespace.
+pub fn list_of<T: FromStr>(s: &str, separator: &str) -> Result<Vec<T>, ParseError> {
+    let items: Box<dyn Iterator<Item = &str>> = if separator.trim().is_empty() {
+        Box::new(s.split_whitespace())
+    } else {
+        Box::new(s.split(separator))
+    };
+    items
+        .map(|item| {
+            let item = item.trim();
+            parse_at(s, offset(s, item), item)
+        })
+        .collect()
+}
+
+/// Checks that `values`, parsed from `s`, has exactly `N` elements.
+pub fn exactly<T, const N: usize>(s: &str, values: Vec<T>) -> Result<[T; N], ParseError> {
+    values.try_into().map_err(|values: Vec<T>| {
+        let (line, column) = position(s, s.trim_end().len());
+        ParseError::new(
+            line,
+            column,
+            ParseErrorKind::WrongLength {
+                expected: N,
+                found: values.len(),
+            },
+        )
+    })
+}
+
+/// Applies `parse` to every line of `input`, reporting errors relative to `input`.
+pub fn each_line<'a, T>(
+    input: &'a str,
+    mut parse: impl FnMut(&'a str) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    input
+        .lines()
+        .map(|line| parse(line).map_err(|err| err.within(input, line)))
+        .collect()
+}
+
+/// Applies `parse` to every block of `input` (see [`blocks`]), reporting errors
+/// relative to `input`.
+pub fn each_block<'a, T>(
+    input: &'a str,
+    mut parse: impl FnMut(&'a str) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    blocks(input)
+        .map(|block| parse(block).map_err(|err| err.within(input, block)))
+        .collect()
+}
+
+/// Applies `parse` to every `separator`-delimited item of `s`, reporting errors
+/// relative to `s`.
+pub fn each_item<'a, T>(
+    s: &'a str,
+    separator: &str,
+    mut parse: impl FnMut(&'a str) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    s.split(separator)
+        .map(|item| parse(item).map_err(|err| err.within(s, item)))
+        .collect()
+}
+
+/// Converts a result into an [`Option`], printing the error if there was one.
+pub fn report<T, E: Display>(result: Result<T, E>) -> Option<T> {
+    result.map_err(|err| eprintln!("{err}")).ok()
+}
+
+fn parse_at<T: FromStr>(s: &str, start: usize, text: &str) -> Result<T, ParseError> {
+    text.parse().map_err(|_| {
+        let (line, column) = position(s, start);
+        ParseError::new(line, column, ParseErrorKind::InvalidValue(text.to_string()))
+    })
+}
+
+/// Byte offset of `part`, which must be a subslice of `s`.
+fn offset(s: &str, part: &str) -> usize {
+    part.as_ptr() as usize - s.as_ptr() as usize
+}
+
+/// 1-based line and column of byte `offset` in `s`.
+fn position(s: &str, offset: usize) -> (usize, usize) {
+    let before = &s[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/* -------------------------------------------------------------------------- */
+
+/// An error which can be returned by the helpers in [`crate::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line of the offending text.
+    pub line: usize,
+    /// 1-based column of the offending text.
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+/// What went wrong in a [`ParseError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The text could not be parsed as the requested type.
+    InvalidValue(String),
+    /// The expected separator did not appear.
+    MissingSeparator(String),
+    /// The next character was not one of those allowed.
+    ExpectedOneOf(String),
+    /// A grid row had a different width from the first row.
+    RaggedGrid { expected: usize, found: usize },
+    /// A list had the wrong number of values.
+    WrongLength { expected: usize, found: usize },
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, kind: ParseErrorKind) -> Self {
+        Self { line, column, kind }
+    }
+
+    /// Moves the position of an error raised while parsing `part`, a subslice of
+    /// `outer`, so that it is relative to `outer`.
+    pub fn within(self, outer: &str, part: &str) -> Self {
+        let (line, column) = position(outer, offset(outer, part));
+        Self {
+            line: self.line + line - 1,
+            column: match self.line {
+                1 => self.column + column - 1,
+                _ => self.column,
+            },
+            ..self
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::InvalidValue(text) => write!(f, "invalid value `{text}`"),
+            ParseErrorKind::MissingSeparator(sep) => write!(f, "expected separator `{sep}`"),
+            ParseErrorKind::ExpectedOneOf(options) => {
+                write!(f, "expected one of `{options}`")
+            }
+            ParseErrorKind::RaggedGrid { expected, found } => {
+                write!(f, "expected a row of width {expected}, found {found}")
+            }
+            ParseErrorKind::WrongLength { expected, found } => {
+                write!(f, "expected {expected} values, found {found}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ints() {
+        assert_eq!(
+            ints::<i64>("1,0,1~1,2,-1").unwrap(),
+            vec![1, 0, 1, 1, 2, -1]
+        );
+        assert_eq!(ints::<i32>("x=3, y=-14..7").unwrap(), vec![3, -14, 7]);
+        assert_eq!(ints::<u8>("a - b").unwrap(), vec![]);
+        assert_eq!(
+            ints::<u8>("1 2\n3 256").unwrap_err(),
+            ParseError::new(2, 3, ParseErrorKind::InvalidValue("256".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_exactly() {
+        assert_eq!(exactly::<u8, 2>("1 2", vec![1, 2]).unwrap(), [1, 2]);
+        assert_eq!(
+            exactly::<u8, 3>("1 2", vec![1, 2]).unwrap_err().to_string(),
+            "line 1, column 4: expected 3 values, found 2"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let input = "a\nb\n\nc\n\n\n\nd\n\n\ne\n";
+        assert_eq!(
+            blocks(input).collect::<Vec<_>>(),
+            vec!["a\nb", "c", "d", "e"]
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(
+            grid("ab\ncd\n").unwrap(),
+            vec![vec!['a', 'b'], vec!['c', 'd']]
+        );
+        assert_eq!(
+            grid("ab\nc\n").unwrap_err(),
+            ParseError::new(
+                2,
+                2,
+                ParseErrorKind::RaggedGrid {
+                    expected: 2,
+                    found: 1
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_key_value_and_list_of() {
+        assert_eq!(key_value("a: b", ": ").unwrap(), ("a", "b"));
+        assert_eq!(list_of::<u32>("3, 4,5", ",").unwrap(), vec![3, 4, 5]);
+        assert_eq!(list_of::<u32>(" 3  4 ", " ").unwrap(), vec![3, 4]);
+        assert_eq!(
+            list_of::<u32>("3, x", ",").unwrap_err().to_string(),
+            "line 1, column 4: invalid value `x`"
+        );
+    }
+
+    #[test]
+    fn test_one_of() {
+        assert_eq!(one_of("a<12", "xmas").unwrap(), (2, "<12"));
+        assert_eq!(
+            one_of("é<1", "xmas").unwrap_err().to_string(),
+            "line 1, column 1: expected one of `xmas`"
+        );
+        assert_eq!(
+            one_of("", "<>").unwrap_err(),
+            ParseError::new(1, 1, ParseErrorKind::ExpectedOneOf("<>".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_positions_are_relative_to_input() {
+        let input = "1 2\n3 4\n\n5 6\n7 y\n";
+        let err =
+            each_block(input, |block| each_line(block, |l| list_of::<u32>(l, " "))).unwrap_err();
+        assert_eq!((err.line, err.column), (5, 3));
+        let err = each_item("a=1;b=x", ";", |item| {
+            let (_, v) = key_value(item, "=")?;
+            value::<u8>(v).map_err(|err| err.within(item, v))
+        })
+        .unwrap_err();
+        assert_eq!((err.line, err.column), (1, 7));
+        assert_eq!(
+            key_value("a\nbc", "=").unwrap_err().to_string(),
+            "line 2, column 3: expected separator `=`"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Areas and lattice point counts of simple polygons with integer vertices.
+//!
+//! Vertices are `(row, col)` pairs in order around the polygon, without repeating the
+//! first vertex at the end.
+
+use num::integer::gcd;
+
+/// Follows steps of `distance` along unit `(row, col)` directions from the origin,
+/// returning the vertex reached after each one. The last vertex is the origin again
+/// when the steps form a closed loop.
+pub fn vertices_from_steps(steps: impl IntoIterator<Item = ((i64, i64), i64)>) -> Vec<(i64, i64)> {
+    steps
+        .into_iter()
+        .scan((0, 0), |(row, col), ((dr, dc), distance)| {
+            (*row, *col) = (*row + dr * distance, *col + dc * distance);
+            Some((*row, *col))
+        })
+        .collect()
+}
+
+fn edges(vertices: &[(i64, i64)]) -> impl Iterator<Item = ((i64, i64), (i64, i64))> + '_ {
+    vertices
+        .iter()
+        .copied()
+        .zip(vertices.iter().copied().cycle().skip(1))
+}
+
+/// Returns twice the area enclosed by the polygon, using the shoelace formula.
+pub fn twice_area(vertices: &[(i64, i64)]) -> u64 {
+    edges(vertices)
+        .map(|((r1, c1), (r2, c2))| c1 * r2 - c2 * r1)
+        .sum::<i64>()
+        .unsigned_abs()
+}
+
+/// Returns the number of lattice points on the polygon's edges.
+pub fn boundary(vertices: &[(i64, i64)]) -> u64 {
+    edges(vertices)
+        .map(|((r1, c1), (r2, c2))| gcd(r2 - r1, c2 - c1).unsigned_abs())
+        .sum()
+}
+
+/// Returns the number of lattice points strictly inside the polygon, by Pick's theorem:
+/// `A = i + b / 2 - 1`. Fewer than three vertices, or collinear ones, enclose nothing.
+pub fn interior(vertices: &[(i64, i64)]) -> u64 {
+    if vertices.len() < 3 {
+        return 0;
+    }
+    (twice_area(vertices) + 2).saturating_sub(boundary(vertices)) / 2
+}
+
+/// Returns the number of lattice points inside or on the polygon.
+pub fn lattice_points(vertices: &[(i64, i64)]) -> u64 {
+    interior(vertices) + boundary(vertices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::read_file;
+
+    #[test]
+    fn test_dig_plan() {
+        let plan = read_file("examples", crate::day!(18));
+        let steps = plan.lines().map(|line| {
+            let mut parts = line.split(' ');
+            let direction = match parts.next().unwrap() {
+                "U" => (-1, 0),
+                "R" => (0, 1),
+                "D" => (1, 0),
+                _ => (0, -1),
+            };
+            (direction, parts.next().unwrap().parse().unwrap())
+        });
+        let vertices = vertices_from_steps(steps);
+        assert_eq!(vertices.last(), Some(&(0, 0)));
+        assert_eq!(boundary(&vertices), 38);
+        assert_eq!(twice_area(&vertices), 84);
+        assert_eq!(interior(&vertices), 24);
+        assert_eq!(lattice_points(&vertices), 62);
+    }
+
+    #[test]
+    fn test_pipe_loop() {
+        // The corners of the loop in day 10's first example, which encloses one tile.
+        let corners = [
+            (2, 0),
+            (2, 1),
+            (1, 1),
+            (1, 2),
+            (0, 2),
+            (0, 3),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+            (3, 1),
+            (4, 1),
+            (4, 0),
+        ];
+        assert_eq!(boundary(&corners), 16);
+        assert_eq!(interior(&corners), 1);
+        assert_eq!(interior(&[(0, 0), (0, 2), (2, 0)]), 0);
+        assert_eq!(twice_area(&[(0, 0), (0, 2), (2, 0)]), 4);
+    }
+
+    #[test]
+    fn test_degenerate() {
+        assert_eq!(interior(&[]), 0);
+        assert_eq!(lattice_points(&[]), 0);
+        assert_eq!(interior(&[(3, 4)]), 0);
+        assert_eq!(interior(&[(0, 0), (0, 5)]), 0);
+        assert_eq!(interior(&[(0, 0), (0, 4), (0, 2)]), 0);
+        assert_eq!(interior(&[(0, 0), (2, 2), (4, 4), (1, 1)]), 0);
+    }
+}
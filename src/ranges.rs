@@ -0,0 +1,237 @@
+//! Sets of integers stored as sorted, disjoint half-open ranges.
+
+use std::ops::Range;
+
+use num::PrimInt;
+
+/// A set of integers stored as a normalised list of half-open ranges.
+///
+/// The ranges are kept sorted, non-empty, and neither overlapping nor touching, so
+/// two sets holding the same values always compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RangeSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T: PrimInt> RangeSet<T> {
+    /// Creates an empty [`RangeSet`].
+    pub fn new() -> Self {
+        Self { ranges: vec![] }
+    }
+
+    /// Returns the normalised ranges of the set, in ascending order.
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    /// Returns the number of values in the set.
+    pub fn len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::zero(), |acc, r| acc + (r.end - r.start))
+    }
+
+    /// Returns `true` if the set holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the smallest value in the set.
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    /// Returns the largest value in the set.
+    pub fn max(&self) -> Option<T> {
+        self.ranges.last().map(|r| r.end - T::one())
+    }
+
+    /// Returns `true` if `value` is in the set.
+    pub fn contains(&self, value: T) -> bool {
+        let i = self.ranges.partition_point(|r| r.end <= value);
+        self.ranges.get(i).is_some_and(|r| r.start <= value)
+    }
+
+    /// Adds every value of `range` to the set.
+    pub fn insert(&mut self, range: Range<T>) {
+        self.ranges.push(range);
+        self.normalise();
+    }
+
+    /// Returns the values that are in `self` or `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        self.ranges.iter().chain(&other.ranges).cloned().collect()
+    }
+
+    /// Returns the values that are in both `self` and `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while let (Some(a), Some(b)) = (self.ranges.get(i), other.ranges.get(j)) {
+            let (start, end) = (a.start.max(b.start), a.end.min(b.end));
+            if start < end {
+                ranges.push(start..end);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { ranges }
+    }
+
+    /// Returns the values that are in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let mut holes = other.ranges.iter().peekable();
+        for range in &self.ranges {
+            let mut start = range.start;
+            while let Some(hole) = holes.peek() {
+                if hole.end <= start {
+                    holes.next();
+                    continue;
+                }
+                if hole.start >= range.end {
+                    break;
+                }
+                if hole.start > start {
+                    ranges.push(start..hole.start);
+                }
+                start = hole.end;
+                if hole.end > range.end {
+                    break;
+                }
+                holes.next();
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+        Self { ranges }
+    }
+
+    /// Splits the set into the values below `at` and the values at or above `at`.
+    pub fn split_at(&self, at: T) -> (Self, Self) {
+        let (mut below, mut above) = (vec![], vec![]);
+        for range in &self.ranges {
+            if range.end <= at {
+                below.push(range.clone());
+            } else if range.start >= at {
+                above.push(range.clone());
+            } else {
+                below.push(range.start..at);
+                above.push(at..range.end);
+            }
+        }
+        (Self { ranges: below }, Self { ranges: above })
+    }
+
+    /// Returns the set with `delta` added to every value.
+    pub fn offset(&self, delta: T) -> Self {
+        Self {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|r| r.start + delta..r.end + delta)
+                .collect(),
+        }
+    }
+
+    /// Returns the set with `delta` subtracted from every value, for unsigned sets.
+    pub fn offset_down(&self, delta: T) -> Self {
+        Self {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|r| r.start - delta..r.end - delta)
+                .collect(),
+        }
+    }
+
+    fn normalise(&mut self) {
+        self.ranges.retain(|r| r.start < r.end);
+        self.ranges.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<T>> = Vec::with_capacity(self.ranges.len());
+        for range in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        self.ranges = merged;
+    }
+}
+
+impl<T: PrimInt> Default for RangeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PrimInt> From<Range<T>> for RangeSet<T> {
+    fn from(range: Range<T>) -> Self {
+        std::iter::once(range).collect()
+    }
+}
+
+impl<T: PrimInt> FromIterator<Range<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = Self {
+            ranges: iter.into_iter().collect(),
+        };
+        set.normalise();
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[Range<i64>]) -> RangeSet<i64> {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_normalise() {
+        let s = set(&[5..8, 0..2, 2..3, 7..10, 12..12]);
+        assert_eq!(s.ranges(), &[0..3, 5..10]);
+        assert_eq!(s.len(), 8);
+        assert_eq!((s.min(), s.max()), (Some(0), Some(9)));
+        assert!(s.contains(2) && !s.contains(3) && s.contains(9) && !s.contains(10));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&[0..10, 20..30]);
+        let b = set(&[5..25, 28..40]);
+        assert_eq!(a.union(&b), RangeSet::from(0..40));
+        assert_eq!(a.intersection(&b), set(&[5..10, 20..25, 28..30]));
+        assert_eq!(a.difference(&b), set(&[0..5, 25..28]));
+        assert_eq!(b.difference(&a), set(&[10..20, 30..40]));
+        assert_eq!(
+            a.difference(&set(&[2..4, 6..8])),
+            set(&[0..2, 4..6, 8..10, 20..30])
+        );
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn test_split_and_offset() {
+        let a = set(&[0..10, 20..30]);
+        assert_eq!(a.split_at(5), (RangeSet::from(0..5), set(&[5..10, 20..30])));
+        assert_eq!(
+            a.split_at(15),
+            (RangeSet::from(0..10), RangeSet::from(20..30))
+        );
+        assert_eq!(a.offset(-5), set(&[-5..5, 15..25]));
+
+        let unsigned: RangeSet<u64> = [10..20, 30..40].into_iter().collect();
+        assert_eq!(
+            unsigned.offset_down(10),
+            [0..10, 20..30].into_iter().collect()
+        );
+        assert_eq!(unsigned.offset_down(10).offset(10), unsigned);
+    }
+}
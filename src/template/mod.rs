@@ -31,10 +31,34 @@ pub fn read_file_part(folder: &str, day: Day, part: u8) -> String {
     f.expect("could not open input file")
 }
 
+/// Helper function that writes `contents` to a text file, creating the folder if needed.
+pub fn write_file(folder: &str, day: Day, contents: &str) -> std::io::Result<()> {
+    write_bytes(folder, &format!("{day}.txt"), contents.as_bytes())
+}
+
+/// Helper function that writes `contents` to `file_name` in a data folder, creating the
+/// folder if needed. E.g. for images like `18-1.ppm`.
+pub fn write_bytes(folder: &str, file_name: &str, contents: &[u8]) -> std::io::Result<()> {
+    let dir = env::current_dir()?.join("data").join(folder);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(file_name), contents)
+}
+
+/// Returns `true` if the solution was started with `--debug`, in which case the debug
+/// function passed to [`solution!`](crate::solution) prints extra diagnostics about how
+/// the day reached its answer.
+#[must_use]
+pub fn debug_enabled() -> bool {
+    env::args().any(|x| x == "--debug")
+}
+
 /// Creates the constant `DAY` and sets up the input and runner for each part.
+///
+/// A day may also name a function taking the input, e.g. `solution!(19, debug)`. It
+/// runs once after both parts when started with `--debug`, outside of any timing.
 #[macro_export]
 macro_rules! solution {
-    ($day:expr) => {
+    ($day:expr $(, $debug:path)?) => {
         /// The current day.
         const DAY: advent_of_code::Day = advent_of_code::day!($day);
 
@@ -43,6 +67,11 @@ macro_rules! solution {
             let input = advent_of_code::template::read_file("inputs", DAY);
             run_part(part_one, &input, DAY, 1);
             run_part(part_two, &input, DAY, 2);
+            $(
+                if advent_of_code::template::debug_enabled() {
+                    $debug(&input);
+                }
+            )?
         }
     };
 }